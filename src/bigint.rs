@@ -0,0 +1,432 @@
+use std::cmp::Ordering;
+use std::convert::Infallible;
+use std::fmt;
+use std::iter::{Product, Sum};
+use std::ops::{Add, AddAssign, Mul, MulAssign};
+use std::str::FromStr;
+
+const BASE: u64 = 1_000_000_000;
+const BASE_DIGITS: usize = 9;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BigUint {
+    limbs: Vec<u32>,
+}
+
+impl BigUint {
+    pub fn zero() -> Self {
+        BigUint { limbs: vec![] }
+    }
+
+    pub fn one() -> Self {
+        BigUint::from(1u64)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    fn normalize(&mut self) {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+    }
+
+    pub fn to_u128(&self) -> Option<u128> {
+        self.limbs.iter().rev().try_fold(0u128, |acc, &limb| {
+            acc.checked_mul(BASE as u128)?.checked_add(limb as u128)
+        })
+    }
+
+    pub fn mul_small(&self, rhs: u32) -> Self {
+        let mut limbs = Vec::with_capacity(self.limbs.len() + 2);
+        let mut carry = 0u64;
+        for &limb in self.limbs.iter() {
+            let v = limb as u64 * rhs as u64 + carry;
+            limbs.push((v % BASE) as u32);
+            carry = v / BASE;
+        }
+        while carry > 0 {
+            limbs.push((carry % BASE) as u32);
+            carry /= BASE;
+        }
+        let mut out = BigUint { limbs };
+        out.normalize();
+        out
+    }
+
+    pub fn add_small(&self, rhs: u32) -> Self {
+        self + &BigUint::from(rhs as u64)
+    }
+
+    pub fn pow(&self, mut exp: u32) -> Self {
+        let mut base = self.clone();
+        let mut out = BigUint::one();
+        while exp > 0 {
+            if exp & 1 == 1 {
+                out = &out * &base;
+            }
+            base = &base * &base;
+            exp >>= 1;
+        }
+        out
+    }
+
+    pub fn checked_sub(&self, rhs: &BigUint) -> Option<BigUint> {
+        if self < rhs {
+            return None;
+        }
+        let mut limbs = Vec::with_capacity(self.limbs.len());
+        let mut borrow = 0i64;
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut v = a as i64 - rhs.limbs.get(i).copied().unwrap_or(0) as i64 - borrow;
+            borrow = (v < 0) as i64;
+            if v < 0 {
+                v += BASE as i64;
+            }
+            limbs.push(v as u32);
+        }
+        let mut out = BigUint { limbs };
+        out.normalize();
+        Some(out)
+    }
+
+    pub fn div_rem(&self, rhs: &BigUint) -> Option<(BigUint, BigUint)> {
+        if rhs.is_zero() {
+            return None;
+        }
+        let mut quotient = vec![0u32; self.limbs.len()];
+        let mut rem = BigUint::zero();
+        for (i, &limb) in self.limbs.iter().enumerate().rev() {
+            rem = rem.mul_small(BASE as u32).add_small(limb);
+            let (mut lo, mut hi) = (0u32, (BASE - 1) as u32);
+            while lo < hi {
+                let mid = lo + (hi - lo).div_ceil(2);
+                if rhs.mul_small(mid) <= rem {
+                    lo = mid;
+                } else {
+                    hi = mid - 1;
+                }
+            }
+            rem = rem.checked_sub(&rhs.mul_small(lo)).unwrap();
+            quotient[i] = lo;
+        }
+        let mut quotient = BigUint { limbs: quotient };
+        quotient.normalize();
+        Some((quotient, rem))
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(mut n: u64) -> Self {
+        let mut limbs = vec![];
+        while n > 0 {
+            limbs.push((n % BASE) as u32);
+            n /= BASE;
+        }
+        BigUint { limbs }
+    }
+}
+
+impl From<u128> for BigUint {
+    fn from(mut n: u128) -> Self {
+        let mut limbs = vec![];
+        while n > 0 {
+            limbs.push((n % BASE as u128) as u32);
+            n /= BASE as u128;
+        }
+        BigUint { limbs }
+    }
+}
+
+impl From<usize> for BigUint {
+    fn from(n: usize) -> Self {
+        BigUint::from(n as u64)
+    }
+}
+
+impl FromStr for BigUint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(format!("'{s}' is not a valid unsigned integer"));
+        }
+        let bytes = s.as_bytes();
+        let mut limbs = vec![];
+        let mut end = bytes.len();
+        while end > 0 {
+            let start = end.saturating_sub(BASE_DIGITS);
+            let chunk = std::str::from_utf8(&bytes[start..end]).unwrap();
+            limbs.push(chunk.parse::<u32>().unwrap());
+            end = start;
+        }
+        let mut out = BigUint { limbs };
+        out.normalize();
+        Ok(out)
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut s = String::new();
+        match self.limbs.split_last() {
+            None => s.push('0'),
+            Some((last, rest)) => {
+                s.push_str(&last.to_string());
+                for limb in rest.iter().rev() {
+                    s.push_str(&format!("{limb:0width$}", width = BASE_DIGITS));
+                }
+            }
+        }
+        f.pad_integral(true, "", &s)
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.limbs
+            .len()
+            .cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Add<&BigUint> for &BigUint {
+    type Output = BigUint;
+
+    fn add(self, rhs: &BigUint) -> BigUint {
+        let len = self.limbs.len().max(rhs.limbs.len());
+        let mut limbs = Vec::with_capacity(len + 1);
+        let mut carry = 0u64;
+        for i in 0..len {
+            let a = self.limbs.get(i).copied().unwrap_or(0) as u64;
+            let b = rhs.limbs.get(i).copied().unwrap_or(0) as u64;
+            let v = a + b + carry;
+            limbs.push((v % BASE) as u32);
+            carry = v / BASE;
+        }
+        if carry > 0 {
+            limbs.push(carry as u32);
+        }
+        BigUint { limbs }
+    }
+}
+
+impl Add for BigUint {
+    type Output = BigUint;
+
+    fn add(self, rhs: BigUint) -> BigUint {
+        &self + &rhs
+    }
+}
+
+impl AddAssign<&BigUint> for BigUint {
+    fn add_assign(&mut self, rhs: &BigUint) {
+        *self = &*self + rhs;
+    }
+}
+
+impl Mul<&BigUint> for &BigUint {
+    type Output = BigUint;
+
+    fn mul(self, rhs: &BigUint) -> BigUint {
+        if self.is_zero() || rhs.is_zero() {
+            return BigUint::zero();
+        }
+        let mut acc = vec![0u64; self.limbs.len() + rhs.limbs.len() + 1];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &b) in rhs.limbs.iter().enumerate() {
+                let v = acc[i + j] + a as u64 * b as u64 + carry;
+                acc[i + j] = v % BASE;
+                carry = v / BASE;
+            }
+            let mut k = i + rhs.limbs.len();
+            while carry > 0 {
+                let v = acc[k] + carry;
+                acc[k] = v % BASE;
+                carry = v / BASE;
+                k += 1;
+            }
+        }
+        let mut out = BigUint {
+            limbs: acc.into_iter().map(|v| v as u32).collect(),
+        };
+        out.normalize();
+        out
+    }
+}
+
+impl Mul for BigUint {
+    type Output = BigUint;
+
+    fn mul(self, rhs: BigUint) -> BigUint {
+        &self * &rhs
+    }
+}
+
+impl MulAssign<&BigUint> for BigUint {
+    fn mul_assign(&mut self, rhs: &BigUint) {
+        *self = &*self * rhs;
+    }
+}
+
+impl Sum for BigUint {
+    fn sum<I: Iterator<Item = BigUint>>(iter: I) -> Self {
+        iter.fold(BigUint::zero(), |acc, v| &acc + &v)
+    }
+}
+
+impl<'a> Sum<&'a BigUint> for BigUint {
+    fn sum<I: Iterator<Item = &'a BigUint>>(iter: I) -> Self {
+        iter.fold(BigUint::zero(), |acc, v| &acc + v)
+    }
+}
+
+impl Product for BigUint {
+    fn product<I: Iterator<Item = BigUint>>(iter: I) -> Self {
+        iter.fold(BigUint::one(), |acc, v| &acc * &v)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Overflow;
+
+pub trait Natural: Clone + Ord + fmt::Display {
+    type Overflow: fmt::Debug + PartialEq;
+
+    fn zero() -> Self;
+    fn one() -> Self;
+    fn from_usize(n: usize) -> Self;
+    fn add_exact(&self, rhs: &Self) -> Result<Self, Self::Overflow>;
+    fn mul_exact(&self, rhs: &Self) -> Result<Self, Self::Overflow>;
+    fn pow_exact(&self, exp: u32) -> Result<Self, Self::Overflow>;
+    fn try_sub(&self, rhs: &Self) -> Option<Self>;
+    fn try_div(&self, rhs: &Self) -> Option<Self>;
+
+    fn try_add(&self, rhs: &Self) -> Option<Self> {
+        self.add_exact(rhs).ok()
+    }
+    fn try_mul(&self, rhs: &Self) -> Option<Self> {
+        self.mul_exact(rhs).ok()
+    }
+}
+
+impl Natural for usize {
+    type Overflow = Overflow;
+
+    fn zero() -> Self {
+        0
+    }
+    fn one() -> Self {
+        1
+    }
+    fn from_usize(n: usize) -> Self {
+        n
+    }
+    fn add_exact(&self, rhs: &Self) -> Result<Self, Overflow> {
+        self.checked_add(*rhs).ok_or(Overflow)
+    }
+    fn mul_exact(&self, rhs: &Self) -> Result<Self, Overflow> {
+        self.checked_mul(*rhs).ok_or(Overflow)
+    }
+    fn try_sub(&self, rhs: &Self) -> Option<Self> {
+        self.checked_sub(*rhs)
+    }
+    fn try_div(&self, rhs: &Self) -> Option<Self> {
+        self.checked_div(*rhs)
+    }
+    fn pow_exact(&self, exp: u32) -> Result<Self, Overflow> {
+        self.checked_pow(exp).ok_or(Overflow)
+    }
+}
+
+impl Natural for BigUint {
+    type Overflow = Infallible;
+
+    fn zero() -> Self {
+        BigUint::zero()
+    }
+    fn one() -> Self {
+        BigUint::one()
+    }
+    fn from_usize(n: usize) -> Self {
+        BigUint::from(n)
+    }
+    fn add_exact(&self, rhs: &Self) -> Result<Self, Infallible> {
+        Ok(self + rhs)
+    }
+    fn mul_exact(&self, rhs: &Self) -> Result<Self, Infallible> {
+        Ok(self * rhs)
+    }
+    fn try_sub(&self, rhs: &Self) -> Option<Self> {
+        self.checked_sub(rhs)
+    }
+    fn try_div(&self, rhs: &Self) -> Option<Self> {
+        self.div_rem(rhs).map(|(q, _)| q)
+    }
+    fn pow_exact(&self, exp: u32) -> Result<Self, Infallible> {
+        Ok(self.pow(exp))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arithmetic_matches_u128() {
+        let values = [
+            0u64,
+            1,
+            999_999_999,
+            1_000_000_000,
+            123_456_789_012,
+            u64::MAX,
+        ];
+        for &a in values.iter() {
+            for &b in values.iter() {
+                let (ba, bb) = (BigUint::from(a), BigUint::from(b));
+                assert_eq!((&ba + &bb).to_u128(), Some(a as u128 + b as u128));
+                assert_eq!((&ba * &bb).to_u128(), Some(a as u128 * b as u128));
+                assert_eq!(ba.cmp(&bb), a.cmp(&b));
+                assert_eq!(
+                    ba.checked_sub(&bb).map(|v| v.to_u128()),
+                    a.checked_sub(b).map(|v| Some(v as u128))
+                );
+                let expected = a.checked_div(b).zip(a.checked_rem(b));
+                let quotient = ba.div_rem(&bb).map(|(q, r)| (q.to_u128(), r.to_u128()));
+                assert_eq!(
+                    quotient,
+                    expected.map(|(q, r)| (Some(q as u128), Some(r as u128)))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn display_and_parse() {
+        let big = BigUint::from(u128::MAX).mul_small(10).add_small(7);
+        assert_eq!(big.to_string(), format!("{}7", u128::MAX));
+        assert_eq!(big.to_u128(), None);
+        assert_eq!("3402823669209384634633746074317682114557".parse(), Ok(big));
+        assert_eq!("000120".parse::<BigUint>().unwrap().to_string(), "120");
+        assert_eq!(BigUint::zero().to_string(), "0");
+        assert_eq!(
+            BigUint::from(2u64).pow(100).to_string(),
+            "1267650600228229401496703205376"
+        );
+        assert!("12a".parse::<BigUint>().is_err());
+        let big = BigUint::from(3u64).pow(90);
+        let (q, r) = big.div_rem(&BigUint::from(3u64).pow(41)).unwrap();
+        assert_eq!((q, r), (BigUint::from(3u64).pow(49), BigUint::zero()));
+        assert_eq!(big.div_rem(&BigUint::zero()), None);
+    }
+}
@@ -6,12 +6,113 @@ mod day05;
 mod day06;
 mod day07;
 
+pub use day05::{Day05Query, run_query as run_day05_query};
+
+use crate::trace::Tracer;
+use crate::visual::Visualizer;
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::str::FromStr;
+
 #[derive(Clone, Copy, Debug)]
 pub enum Part {
     One,
     Two,
 }
 
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    values: HashMap<String, String>,
+    tracer: Tracer,
+    visualizer: Visualizer,
+}
+
+impl Options {
+    pub fn parse(pairs: &[String]) -> Result<Self, String> {
+        let mut values = HashMap::new();
+        for pair in pairs.iter() {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, "true"));
+            if key.is_empty() {
+                return Err(format!("'{pair}' is not a valid option"));
+            }
+            values.insert(key.to_string(), value.to_string());
+        }
+        Ok(Options {
+            values,
+            tracer: Tracer::default(),
+            visualizer: Visualizer::default(),
+        })
+    }
+
+    pub fn with_tracer(mut self, tracer: Tracer) -> Self {
+        self.tracer = tracer;
+        self
+    }
+
+    pub fn tracer(&self) -> &Tracer {
+        &self.tracer
+    }
+
+    pub fn with_visualizer(mut self, visualizer: Visualizer) -> Self {
+        self.visualizer = visualizer;
+        self
+    }
+
+    pub fn visualizer(&self) -> &Visualizer {
+        &self.visualizer
+    }
+
+    pub fn get<T: FromStr>(&self, key: &str) -> Result<Option<T>, String> {
+        self.values
+            .get(key)
+            .map(|v| {
+                v.parse::<T>()
+                    .map_err(|_| format!("'{v}' is not a valid value for option '{key}'"))
+            })
+            .transpose()
+    }
+
+    pub fn get_or<T: FromStr>(&self, key: &str, default: T) -> Result<T, String> {
+        Ok(self.get(key)?.unwrap_or(default))
+    }
+
+    pub fn flag(&self, key: &str) -> bool {
+        self.values.get(key).is_some_and(|v| v != "false")
+    }
+
+    pub fn check_retired(&self, retired: &[(&str, &str)]) -> Result<(), String> {
+        let mut found: Vec<_> = retired
+            .iter()
+            .filter(|(key, _)| self.values.contains_key(*key))
+            .collect();
+        found.sort_unstable();
+        match found.first() {
+            None => Ok(()),
+            Some((key, hint)) => Err(format!("Option '{key}' {hint}")),
+        }
+    }
+
+    pub fn check(&self, known: &[&str]) -> Result<(), String> {
+        let mut unknown: Vec<&str> = self
+            .values
+            .keys()
+            .map(String::as_str)
+            .filter(|key| !known.contains(key))
+            .collect();
+        unknown.sort_unstable();
+        match unknown.first() {
+            None => Ok(()),
+            Some(key) if known.is_empty() => {
+                Err(format!("Unknown option '{key}', this day takes no options"))
+            }
+            Some(key) => Err(format!(
+                "Unknown option '{key}', expected one of {}",
+                known.join(", ")
+            )),
+        }
+    }
+}
+
 pub trait AdventDay {
     fn solve_part1(&self, input: &str) -> String;
     fn solve_part2(&self, input: &str) -> String;
@@ -21,6 +122,48 @@ pub trait AdventDay {
             Part::Two => self.solve_part2(input),
         }
     }
+    fn options(&self) -> &'static [&'static str] {
+        &[]
+    }
+    fn retired_options(&self) -> &'static [(&'static str, &'static str)] {
+        &[]
+    }
+    fn solve_with(&self, part: Part, input: &str, _options: &Options) -> Result<String, String> {
+        Ok(self.solve(part, input))
+    }
+    fn streams(&self) -> bool {
+        false
+    }
+    fn solve_stream(
+        &self,
+        part: Part,
+        reader: &mut dyn BufRead,
+        options: &Options,
+    ) -> Result<String, String> {
+        let mut input = String::new();
+        reader
+            .read_to_string(&mut input)
+            .map_err(|e| e.to_string())?;
+        self.solve_with(part, &input, options)
+    }
+}
+
+pub fn grid_size(input: &str, ragged: bool) -> Result<(usize, usize), String> {
+    let widths: Vec<usize> = input
+        .trim_end_matches(['\n', '\r'])
+        .lines()
+        .map(|l| l.chars().count())
+        .collect();
+    let height = widths.len();
+    let width = widths.iter().copied().max().unwrap_or(0);
+    if !ragged && let Some((i, w)) = widths.iter().enumerate().find(|&(_, &w)| w != widths[0]) {
+        return Err(format!(
+            "Row {} has {w} columns but row 1 has {}",
+            i + 1,
+            widths[0]
+        ));
+    }
+    Ok((width, height))
 }
 
 pub fn get_day(day: u8) -> Option<Box<dyn AdventDay>> {
@@ -1,4 +1,6 @@
-use crate::days::AdventDay;
+use crate::days::{AdventDay, Options, Part};
+use crate::math::{add_mod, sub_mod};
+use crate::trace::{Tracer, trace};
 
 pub struct Day01;
 
@@ -18,89 +20,143 @@ impl Command {
             Command::R(v)
         }
     }
-    fn extract_rotations(&mut self, max: usize) -> usize {
-        let div = max + 1;
-        match self {
-            Command::L(v) | Command::R(v) => {
-                let times = *v / div;
-                *v = v.rem_euclid(div);
-                times
-            }
-        }
-    }
 }
 
-struct Dial {
-    arrow: usize,
-    max: usize,
+#[derive(Clone, Copy, Debug)]
+struct DialConfig {
+    size: usize,
+    start: usize,
+    target: usize,
 }
 
-fn wrapping_sub(a: usize, b: usize, m: usize) -> usize {
-    (a as isize - b as isize).rem_euclid(m as isize) as usize
+impl Default for DialConfig {
+    fn default() -> Self {
+        DialConfig {
+            size: 100,
+            start: 50,
+            target: 0,
+        }
+    }
 }
 
-fn wrapping_add(a: usize, b: usize, m: usize) -> usize {
-    (a + b).rem_euclid(m)
+impl DialConfig {
+    fn from_options(options: &Options) -> Result<Self, String> {
+        let default = DialConfig::default();
+        let config = DialConfig {
+            size: options.get_or("size", default.size)?,
+            start: options.get_or("start", default.start)?,
+            target: options.get_or("target", default.target)?,
+        };
+        if config.size == 0 {
+            return Err("Dial size must be at least 1".to_string());
+        }
+        if config.start >= config.size || config.target >= config.size {
+            return Err(format!(
+                "Dial start and target must be below the dial size {}",
+                config.size
+            ));
+        }
+        Ok(config)
+    }
+}
+
+struct Dial {
+    arrow: usize,
+    size: usize,
+    target: usize,
 }
 
 impl Dial {
-    fn new(arrow: usize, max: usize) -> Self {
-        Dial { arrow, max }
+    fn new(config: &DialConfig) -> Self {
+        Dial {
+            arrow: config.start,
+            size: config.size,
+            target: config.target,
+        }
     }
 
-    fn pass_through_zero(&self, command: &Command) -> bool {
-        match *command {
-            Command::L(v) => self.arrow != 0 && self.arrow < v,
-            Command::R(v) => self.arrow + v > (self.max + 1),
+    fn clicks_on_target(&self, command: &Command) -> usize {
+        let (distance, v) = match *command {
+            Command::L(v) => (sub_mod(self.arrow, self.target, self.size), v),
+            Command::R(v) => (sub_mod(self.target, self.arrow, self.size), v),
+        };
+        let first = if distance == 0 { self.size } else { distance };
+        if v >= first {
+            (v - first) / self.size + 1
+        } else {
+            0
         }
     }
 
     fn rotate(&mut self, command: &Command) {
         match *command {
             Command::L(v) => {
-                self.arrow = wrapping_sub(self.arrow, v, self.max + 1);
+                self.arrow = sub_mod(self.arrow, v, self.size);
             }
             Command::R(v) => {
-                self.arrow = wrapping_add(self.arrow, v, self.max + 1);
+                self.arrow = add_mod(self.arrow, v, self.size);
             }
         }
     }
+
+    fn on_target(&self) -> bool {
+        self.arrow == self.target
+    }
+
     fn arrow(&self) -> usize {
         self.arrow
     }
-    fn max(&self) -> usize {
-        self.max
+}
+
+fn count_hits(input: &str, part: Part, config: &DialConfig, tracer: &Tracer) -> usize {
+    let mut out = 0;
+    let mut dial = Dial::new(config);
+    for line in input.lines() {
+        let command = Command::from_str(line);
+        let hits = match part {
+            Part::One => {
+                dial.rotate(&command);
+                dial.on_target() as usize
+            }
+            Part::Two => {
+                let hits = dial.clicks_on_target(&command);
+                dial.rotate(&command);
+                hits
+            }
+        };
+        out += hits;
+        trace!(
+            tracer,
+            "day01.rotate",
+            command = format!("{command:?}"),
+            position = dial.arrow(),
+            hits = hits,
+            total = out
+        );
     }
+    out
 }
 
 impl AdventDay for Day01 {
     fn solve_part1(&self, input: &str) -> String {
-        let mut out = 0;
-        let mut dial = Dial::new(50, 99);
-        for line in input.lines() {
-            let command = Command::from_str(line);
-            dial.rotate(&command);
-            out += (dial.arrow() == 0) as usize;
-        }
-        out.to_string()
+        count_hits(input, Part::One, &DialConfig::default(), &Tracer::default()).to_string()
     }
     fn solve_part2(&self, input: &str) -> String {
-        let mut out = 0;
-        let mut dial = Dial::new(50, 99);
-        for line in input.lines() {
-            let mut command = Command::from_str(line);
-            out += command.extract_rotations(dial.max());
-            out += (dial.pass_through_zero(&command)) as usize;
-            dial.rotate(&command);
-            out += (dial.arrow() == 0) as usize;
-        }
-        out.to_string()
+        count_hits(input, Part::Two, &DialConfig::default(), &Tracer::default()).to_string()
+    }
+    fn options(&self) -> &'static [&'static str] {
+        &["size", "start", "target", "trace"]
+    }
+    fn solve_with(&self, part: Part, input: &str, options: &Options) -> Result<String, String> {
+        let config = DialConfig::from_options(options)?;
+        Ok(count_hits(input, part, &config, options.tracer()).to_string())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::math::XorShift;
 
     const INPUT: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/examples/day01.txt"));
 
@@ -117,4 +173,66 @@ mod tests {
         let result = day.solve_part2(INPUT);
         assert_eq!(result, "6");
     }
+
+    #[test]
+    fn unknown_options_are_rejected() {
+        let known = Day01.options();
+        let options = Options::parse(&["size=10".to_string()]).unwrap();
+        assert_eq!(options.check(known), Ok(()));
+        let options = Options::parse(&["size=10".to_string(), "sise=5".to_string()]).unwrap();
+        assert_eq!(
+            options.check(known),
+            Err("Unknown option 'sise', expected one of size, start, target, trace".to_string())
+        );
+        assert!(options.check(&[]).is_err());
+    }
+
+    fn simulate_clicks(input: &str, part: Part, config: &DialConfig) -> usize {
+        let mut arrow = config.start;
+        let mut out = 0;
+        for line in input.lines() {
+            let (steps, forward) = match Command::from_str(line) {
+                Command::L(v) => (v, false),
+                Command::R(v) => (v, true),
+            };
+            for _ in 0..steps {
+                arrow = if forward {
+                    (arrow + 1) % config.size
+                } else {
+                    (arrow + config.size - 1) % config.size
+                };
+                out += (matches!(part, Part::Two) && arrow == config.target) as usize;
+            }
+            out += (matches!(part, Part::One) && arrow == config.target) as usize;
+        }
+        out
+    }
+
+    #[test]
+    fn closed_form_matches_click_simulation() {
+        let mut rng = XorShift::new(0x2545_f491_4f6c_dd1d);
+        let mut next = |bound: usize| (rng.next() % bound as u64) as usize;
+        for _ in 0..200 {
+            let size = 1 + next(40);
+            let config = DialConfig {
+                size,
+                start: next(size),
+                target: next(size),
+            };
+            let input: Vec<String> = (0..1 + next(20))
+                .map(|_| {
+                    let dir = if next(2) == 0 { 'L' } else { 'R' };
+                    format!("{dir}{}", next(150))
+                })
+                .collect();
+            let input = input.join("\n");
+            for part in [Part::One, Part::Two] {
+                assert_eq!(
+                    count_hits(&input, part, &config, &Tracer::default()),
+                    simulate_clicks(&input, part, &config),
+                    "{config:?} {part:?}\n{input}"
+                );
+            }
+        }
+    }
 }
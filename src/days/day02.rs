@@ -1,11 +1,98 @@
-use crate::days::AdventDay;
+use crate::days::{AdventDay, Options, Part};
+use crate::math::{digit_count_base, pow, repeat_pattern_base};
+use std::ops::{Add, AddAssign, Sub};
+use std::str::FromStr;
 
 pub struct Day02;
 
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Tally {
+    count: u128,
+    sum: u128,
+}
+
+impl Add for Tally {
+    type Output = Tally;
+
+    fn add(self, rhs: Tally) -> Tally {
+        Tally {
+            count: self.count + rhs.count,
+            sum: self.sum + rhs.sum,
+        }
+    }
+}
+
+impl AddAssign for Tally {
+    fn add_assign(&mut self, rhs: Tally) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Tally {
+    type Output = Tally;
+
+    fn sub(self, rhs: Tally) -> Tally {
+        Tally {
+            count: self.count - rhs.count,
+            sum: self.sum - rhs.sum,
+        }
+    }
+}
+
+fn divisors(n: u32) -> impl Iterator<Item = u32> {
+    (1..=n).filter(move |d| n.is_multiple_of(*d))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Rule {
+    Exactly(u32),
+    AtLeast(u32),
+    AtMost(u32),
+    Block(u32),
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = s
+            .split_once(':')
+            .ok_or_else(|| format!("Rule '{s}' must look like 'name:value'"))?;
+        let value: u32 = value
+            .parse()
+            .ok()
+            .filter(|&v| v > 0)
+            .ok_or_else(|| format!("'{value}' is not a valid repetition parameter"))?;
+        match name {
+            "exactly" => Ok(Rule::Exactly(value)),
+            "at-least" => Ok(Rule::AtLeast(value)),
+            "at-most" => Ok(Rule::AtMost(value)),
+            "block" => Ok(Rule::Block(value)),
+            _ => Err(format!(
+                "Unknown rule '{name}', expected exactly, at-least, at-most or block"
+            )),
+        }
+    }
+}
+
+impl Rule {
+    // Whether an ID of `len` digits whose shortest repeating block has
+    // `period` digits satisfies the rule.
+    fn accepts(&self, len: u32, period: u32) -> bool {
+        let repetitions = len / period;
+        match *self {
+            Rule::Exactly(k) => repetitions.is_multiple_of(k),
+            Rule::AtLeast(k) => repetitions >= k,
+            Rule::AtMost(k) => (2..=k).any(|r| repetitions.is_multiple_of(r)),
+            Rule::Block(b) => len.is_multiple_of(b) && len / b >= 2 && b.is_multiple_of(period),
+        }
+    }
+}
+
 #[derive(Debug)]
 struct Range {
     start: usize,
-    end: usize
+    end: usize,
 }
 
 impl Range {
@@ -15,68 +102,96 @@ impl Range {
     fn from_str(s: &str) -> Self {
         let mut it = s.trim().split("-").filter_map(|n| n.parse::<usize>().ok());
         let (start, end) = (it.next().unwrap(), it.next().unwrap());
-        Range::new(start, end)        
+        Range::new(start, end)
     }
-    fn sum_invalid<F>(&self, f: F) -> usize 
-    where 
-        F: Fn(&usize) -> bool
-    {
-        (self.start..=self.end).filter(f).sum()
+
+    fn lengths(&self, base: u128) -> std::ops::RangeInclusive<u32> {
+        digit_count_base(self.start as u128, base)..=digit_count_base(self.end as u128, base)
     }
-}
 
+    fn periodic(&self, len: u32, block_len: u32, base: u128) -> Tally {
+        let multiplier = repeat_pattern_base(1, block_len, len / block_len, base).unwrap();
+        let lo = pow(base, block_len - 1).max((self.start as u128).div_ceil(multiplier));
+        let hi = (pow(base, block_len) - 1).min(self.end as u128 / multiplier);
+        if lo > hi {
+            return Tally::default();
+        }
+        let count = hi - lo + 1;
+        Tally {
+            count,
+            sum: multiplier * ((lo + hi) * count / 2),
+        }
+    }
 
-fn repeats_twice(number: &usize) -> bool {
-    let s = number.to_string();
-    let len = s.len();
-    if len % 2 == 0 {
-        let half_len = len / 2;
-        let div = 10usize.pow(half_len as u32);
-        (*number / div) == (*number % div)
-    } else {
-        false
+    fn primitive(&self, len: u32, block_len: u32, base: u128) -> Tally {
+        divisors(block_len)
+            .filter(|&d| d < block_len)
+            .fold(self.periodic(len, block_len, base), |acc, d| {
+                acc - self.primitive(len, d, base)
+            })
     }
-}
 
-fn repeats_at_least_twice(number: &usize) -> bool {
-    let s = number.to_string();
-    let len = s.len();
-
-    for k in 1..=(len / 2) { 
-        
-        if len % k != 0 {
-            continue;
-        }
-
-        let base_sequence = &s[0..k];
-        let num_repetitions = len / k;
-        
-        let mut matches = true;        
-        for r in 1..num_repetitions {
-            let start_index = r * k;
-            let end_index = (r + 1) * k;
-            let current_segment = &s[start_index..end_index];
-            
-            if current_segment != base_sequence {
-                matches = false;
-                break;
+    fn tally(&self, rule: Rule, base: u128) -> Tally {
+        let mut out = Tally::default();
+        for len in self.lengths(base) {
+            for period in divisors(len).filter(|&p| rule.accepts(len, p)) {
+                out += self.primitive(len, period, base);
             }
         }
+        out
+    }
+}
+
+fn parse_ranges(input: &str) -> Vec<Range> {
+    input
+        .split(",")
+        .filter(|s| !s.trim().is_empty())
+        .map(Range::from_str)
+        .collect()
+}
 
-        if matches {
-            return true;
+fn total(input: &str, rule: Rule, base: u128, report: bool) -> Tally {
+    let mut out = Tally::default();
+    for range in parse_ranges(input) {
+        let tally = range.tally(rule, base);
+        if report {
+            println!(
+                "{:>12}-{:<12} count {:>6} | sum {}",
+                range.start, range.end, tally.count, tally.sum
+            );
         }
+        out += tally;
     }
-    
-    false
+    out
 }
 
 impl AdventDay for Day02 {
     fn solve_part1(&self, input: &str) -> String {
-        input.split(",").map(|s| Range::from_str(s).sum_invalid(repeats_twice)).sum::<usize>().to_string()
+        total(input, Rule::Exactly(2), 10, false).sum.to_string()
     }
     fn solve_part2(&self, input: &str) -> String {
-        input.split(",").map(|s| Range::from_str(s).sum_invalid(repeats_at_least_twice)).sum::<usize>().to_string()
+        total(input, Rule::AtLeast(2), 10, false).sum.to_string()
+    }
+    fn options(&self) -> &'static [&'static str] {
+        &["rule", "base", "report", "count"]
+    }
+
+    fn solve_with(&self, part: Part, input: &str, options: &Options) -> Result<String, String> {
+        let default = match part {
+            Part::One => Rule::Exactly(2),
+            Part::Two => Rule::AtLeast(2),
+        };
+        let rule = options.get_or("rule", default)?;
+        let base: u128 = options.get_or("base", 10)?;
+        if !(2..=36).contains(&base) {
+            return Err(format!("Base must be between 2 and 36, but got {base}"));
+        }
+        let tally = total(input, rule, base, options.flag("report"));
+        if options.flag("count") {
+            Ok(tally.count.to_string())
+        } else {
+            Ok(tally.sum.to_string())
+        }
     }
 }
 
@@ -99,4 +214,144 @@ mod tests {
         let result = day.solve_part2(INPUT);
         assert_eq!(result, "4174379265");
     }
+
+    impl Range {
+        fn sum_invalid<F>(&self, f: F) -> usize
+        where
+            F: Fn(&usize) -> bool,
+        {
+            (self.start..=self.end).filter(f).sum()
+        }
+    }
+
+    // The baseline brute force, kept as written as the oracle for `tally`.
+    #[allow(clippy::manual_is_multiple_of)]
+    fn repeats_twice(number: &usize) -> bool {
+        let s = number.to_string();
+        let len = s.len();
+        if len % 2 == 0 {
+            let half_len = len / 2;
+            let div = 10usize.pow(half_len as u32);
+            (*number / div) == (*number % div)
+        } else {
+            false
+        }
+    }
+
+    #[allow(clippy::manual_is_multiple_of)]
+    fn repeats_at_least_twice(number: &usize) -> bool {
+        let s = number.to_string();
+        let len = s.len();
+
+        for k in 1..=(len / 2) {
+            if len % k != 0 {
+                continue;
+            }
+
+            let base_sequence = &s[0..k];
+            let num_repetitions = len / k;
+
+            let mut matches = true;
+            for r in 1..num_repetitions {
+                let start_index = r * k;
+                let end_index = (r + 1) * k;
+                let current_segment = &s[start_index..end_index];
+
+                if current_segment != base_sequence {
+                    matches = false;
+                    break;
+                }
+            }
+
+            if matches {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn digits_of(number: usize, base: usize) -> Vec<usize> {
+        let mut digits = vec![];
+        let mut n = number;
+        while n > 0 || digits.is_empty() {
+            digits.push(n % base);
+            n /= base;
+        }
+        digits
+    }
+
+    fn has_period(digits: &[usize], k: usize) -> bool {
+        digits.len().is_multiple_of(k) && digits[k..] == digits[..digits.len() - k]
+    }
+
+    fn brute_force(rule: Rule, number: usize, base: usize) -> bool {
+        let digits = digits_of(number, base);
+        let len = digits.len();
+        let splits = |r: usize| r >= 1 && len.is_multiple_of(r) && has_period(&digits, len / r);
+        match rule {
+            Rule::Exactly(k) => splits(k as usize),
+            Rule::AtLeast(k) => (k as usize..=len).any(splits),
+            Rule::AtMost(k) => (2..=k as usize).any(splits),
+            Rule::Block(b) => len > b as usize && has_period(&digits, b as usize),
+        }
+    }
+
+    #[test]
+    fn enumeration_matches_brute_force() {
+        for (start, end) in [
+            (1, 10_000),
+            (95, 115),
+            (998, 1012),
+            (123_000, 130_000),
+            (5, 5),
+        ] {
+            let range = Range::new(start, end);
+            assert_eq!(
+                range.tally(Rule::Exactly(2), 10).sum,
+                range.sum_invalid(repeats_twice) as u128
+            );
+            assert_eq!(
+                range.tally(Rule::AtLeast(2), 10).sum,
+                range.sum_invalid(repeats_at_least_twice) as u128
+            );
+        }
+    }
+
+    #[test]
+    fn enumeration_handles_wide_ranges() {
+        let range = Range::new(1, 9_999_999_999);
+        assert_eq!(
+            range.tally(Rule::Exactly(2), 10).count,
+            9 + 90 + 900 + 9_000 + 90_000
+        );
+        assert!(range.tally(Rule::AtLeast(2), 10).count > range.tally(Rule::Exactly(2), 10).count);
+    }
+
+    #[test]
+    fn rules_match_brute_force() {
+        let range = Range::new(1, 70_000);
+        let rules = [
+            (Rule::Exactly(3), 10),
+            (Rule::AtLeast(3), 10),
+            (Rule::AtMost(2), 10),
+            (Rule::AtMost(3), 2),
+            (Rule::Block(2), 10),
+            (Rule::AtLeast(2), 2),
+            (Rule::Exactly(2), 16),
+        ];
+        for (rule, base) in rules {
+            let expected: Vec<usize> = (range.start..=range.end)
+                .filter(|&n| brute_force(rule, n, base))
+                .collect();
+            let tally = range.tally(rule, base as u128);
+            assert_eq!(tally.count, expected.len() as u128, "{rule:?} base {base}");
+            assert_eq!(tally.sum, expected.iter().sum::<usize>() as u128);
+        }
+        let single = Range::new(1111, 1111);
+        assert_eq!(single.tally(Rule::AtMost(2), 10).count, 1);
+        assert_eq!(single.tally(Rule::Exactly(2), 10).count, 1);
+        assert_eq!("at-most:3".parse(), Ok(Rule::AtMost(3)));
+        assert!("twice:2".parse::<Rule>().is_err());
+    }
 }
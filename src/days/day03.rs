@@ -1,4 +1,5 @@
-use crate::days::AdventDay;
+use crate::bigint::{BigUint, Natural};
+use crate::days::{AdventDay, Options, Part};
 use std::fmt;
 
 pub struct Day03;
@@ -26,12 +27,9 @@ impl BatterySection {
     fn down(&mut self, times: usize) {
         self.digits = self.digits.saturating_sub(times);
     }
-    fn to_number(&self) -> usize {
-        let mut number = 0usize;
-        for i in 0..self.digits {
-            number += self.value as usize * 10usize.pow(i as u32);
-        }
-        number
+    fn append_to<T: Natural>(&self, number: T) -> Option<T> {
+        let (ten, value) = (T::from_usize(10), T::from_usize(self.value as usize));
+        (0..self.digits).try_fold(number, |acc, _| acc.try_mul(&ten)?.try_add(&value))
     }
 }
 
@@ -123,42 +121,143 @@ impl Battery {
         }
     }
 
-    fn to_number(&self) -> usize {
-        let mut number = 0;
-        let mut digits = 0;
-        for bs in self.sections.iter().rev() {
-            number += bs.to_number() * 10usize.pow(digits);
-            digits += bs.digits as u32;
+    fn to_number<T: Natural>(&self) -> Option<T> {
+        self.sections
+            .iter()
+            .try_fold(T::zero(), |number, bs| bs.append_to(number))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Selection {
+    digits: Vec<u8>,
+    indices: Vec<usize>,
+}
+
+impl Selection {
+    fn to_number<T: Natural>(&self) -> Option<T> {
+        let ten = T::from_usize(10);
+        self.digits.iter().try_fold(T::zero(), |acc, &d| {
+            acc.try_mul(&ten)?.try_add(&T::from_usize(d as usize))
+        })
+    }
+}
+
+fn select_highest(digits: &[u8], k: usize) -> Selection {
+    let mut drops = digits.len().saturating_sub(k);
+    let mut stack: Vec<usize> = Vec::with_capacity(digits.len());
+    for (i, &d) in digits.iter().enumerate() {
+        while drops > 0 && stack.last().is_some_and(|&top| digits[top] < d) {
+            stack.pop();
+            drops -= 1;
         }
-        number
+        stack.push(i);
+    }
+    stack.truncate(k);
+    Selection {
+        digits: stack.iter().map(|&i| digits[i]).collect(),
+        indices: stack,
+    }
+}
+
+fn parse_digits(line: &str) -> Vec<u8> {
+    line.chars()
+        .filter_map(|c| c.to_digit(10).map(|n| n as u8))
+        .collect()
+}
+
+fn describe_bank(line: &str, k: usize) -> String {
+    let selection = select_highest(&parse_digits(line), k);
+    let mut chosen = selection.indices.iter().peekable();
+    let mut highlighted = String::new();
+    let mut index = 0;
+    for c in line.chars() {
+        if c.is_ascii_digit() {
+            if chosen.next_if_eq(&&index).is_some() {
+                highlighted.push_str(&format!("\x1b[1;32m{c}\x1b[0m"));
+            } else {
+                highlighted.push(c);
+            }
+            index += 1;
+        } else {
+            highlighted.push(c);
+        }
+    }
+    let value: BigUint = selection.to_number().unwrap();
+    format!("{highlighted} -> {value} at {:?}", selection.indices)
+}
+
+fn bank_joltage<T: Natural>(line: &str, digits: usize, reference: bool) -> Option<T> {
+    if reference {
+        let mut battery = Battery::from_str(line);
+        battery.highest_lower(digits);
+        battery.to_number()
+    } else {
+        select_highest(&parse_digits(line), digits).to_number()
+    }
+}
+
+fn total_joltage<T: Natural>(input: &str, digits: usize, reference: bool) -> Option<T> {
+    input.lines().try_fold(T::zero(), |out, line| {
+        out.try_add(&bank_joltage(line, digits, reference)?)
+    })
+}
+
+fn total_joltage_promoting(input: &str, digits: usize, reference: bool) -> String {
+    match total_joltage::<usize>(input, digits, reference) {
+        Some(out) => out.to_string(),
+        None => total_joltage::<BigUint>(input, digits, reference)
+            .unwrap()
+            .to_string(),
     }
 }
 
 impl AdventDay for Day03 {
     fn solve_part1(&self, input: &str) -> String {
-        let mut out = 0;
-        for line in input.lines() {
-            let mut battery = Battery::from_str(line);
-            battery.highest_lower(2);
-            out += battery.to_number();
-        }
-        out.to_string()
+        total_joltage_promoting(input, 2, false)
     }
 
     fn solve_part2(&self, input: &str) -> String {
-        let mut out = 0;
-        for line in input.lines() {
-            let mut battery = Battery::from_str(line);
-            battery.highest_lower(12);
-            out += battery.to_number();
+        total_joltage_promoting(input, 12, false)
+    }
+
+    fn options(&self) -> &'static [&'static str] {
+        &["k", "reference", "banks"]
+    }
+
+    fn solve_with(&self, part: Part, input: &str, options: &Options) -> Result<String, String> {
+        let default = match part {
+            Part::One => 2,
+            Part::Two => 12,
+        };
+        let digits = options.get_or("k", default)?;
+        if digits == 0 {
+            return Err("k must be at least 1".to_string());
+        }
+        for (i, line) in input.lines().enumerate() {
+            let available = parse_digits(line).len();
+            if available < digits {
+                return Err(format!(
+                    "Bank {} has {available} batteries, fewer than k = {digits}",
+                    i + 1
+                ));
+            }
+            if options.flag("banks") {
+                println!("{}", describe_bank(line, digits));
+            }
         }
-        out.to_string()
+        Ok(total_joltage_promoting(
+            input,
+            digits,
+            options.flag("reference"),
+        ))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::math::XorShift;
 
     const INPUT: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/examples/day03.txt"));
 
@@ -175,4 +274,45 @@ mod tests {
         let result = day.solve_part2(INPUT);
         assert_eq!(result, "3121910778619");
     }
+
+    #[test]
+    fn select_highest_picks_indices() {
+        let selection = select_highest(&parse_digits("818181911112111"), 3);
+        assert_eq!(selection.digits, vec![9, 2, 1]);
+        assert_eq!(selection.indices, vec![6, 11, 12]);
+        assert_eq!(selection.to_number::<usize>(), Some(921));
+    }
+
+    #[test]
+    fn select_highest_matches_reference() {
+        let mut rng = XorShift::new(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..300 {
+            let seed = rng.next();
+            let len = 1 + (seed % 40) as usize;
+            let line: String = (0..len)
+                .map(|i| char::from(b'1' + ((seed >> (i % 60)) % 9) as u8))
+                .collect();
+            for k in 1..=len.min(19) {
+                assert_eq!(
+                    bank_joltage::<usize>(&line, k, false),
+                    bank_joltage::<usize>(&line, k, true),
+                    "{line} k={k}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn solve_custom_k_beyond_usize() {
+        let day = Day03;
+        let options = Options::parse(&["k=14".to_string()]).unwrap();
+        let result = day.solve_with(Part::One, INPUT, &options).unwrap();
+        assert_eq!(result, "302292688568619");
+        let line = "9".repeat(30);
+        let options = Options::parse(&["k=25".to_string()]).unwrap();
+        let result = day.solve_with(Part::One, &line, &options).unwrap();
+        assert_eq!(result, "9".repeat(25));
+        let options = Options::parse(&["k=31".to_string()]).unwrap();
+        assert!(day.solve_with(Part::One, &line, &options).is_err());
+    }
 }
@@ -1,106 +1,226 @@
-use crate::days::AdventDay;
+use crate::days::{AdventDay, Options, Part, grid_size};
+use crate::trace::{Tracer, trace};
+use crate::visual::{Frame, Pixel, Rgb, gradient};
 use core::fmt;
-use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
 
 pub struct Day04;
 
-fn neighbours(coord: &(usize, usize)) -> HashSet<(usize, usize)> {
-    let (i, j) = *coord;
-    let directions: [(i8, i8); 8] = [
-        (1, 0),
-        (-1, 0),
-        (0, 1),
-        (0, -1),
-        (1, 1),
-        (-1, -1),
-        (-1, 1),
-        (1, -1),
-    ];
-    let mut set = HashSet::with_capacity(8);
+#[derive(Clone, Debug, PartialEq)]
+enum Neighbourhood {
+    VonNeumann(isize),
+    Moore(isize),
+    Custom(Vec<(isize, isize)>),
+}
+
+impl FromStr for Neighbourhood {
+    type Err = String;
 
-    let start_i = i as i32;
-    let start_j = j as i32;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, arg) = s.split_once(':').unwrap_or((s, ""));
+        let radius = || {
+            if arg.is_empty() {
+                return Ok(1);
+            }
+            arg.parse::<isize>()
+                .ok()
+                .filter(|&r| r > 0)
+                .ok_or_else(|| format!("'{arg}' is not a valid neighbourhood radius"))
+        };
+        match name {
+            "von-neumann" => Ok(Neighbourhood::VonNeumann(radius()?)),
+            "moore" => Ok(Neighbourhood::Moore(radius()?)),
+            "custom" => arg
+                .split(';')
+                .map(|pair| {
+                    let (di, dj) = pair.split_once(',').unwrap_or((pair, ""));
+                    match (di.trim().parse(), dj.trim().parse()) {
+                        (Ok(di), Ok(dj)) => Ok((di, dj)),
+                        _ => Err(format!("'{pair}' is not a valid offset, expected 'di,dj'")),
+                    }
+                })
+                .collect::<Result<_, _>>()
+                .map(Neighbourhood::Custom),
+            _ => Err(format!(
+                "Unknown neighbourhood '{name}', expected von-neumann, moore or custom"
+            )),
+        }
+    }
+}
+
+impl Neighbourhood {
+    fn offsets(&self) -> Vec<(isize, isize)> {
+        match self {
+            Neighbourhood::VonNeumann(r) | Neighbourhood::Moore(r) => {
+                let mut offsets = vec![];
+                for di in -r..=*r {
+                    for dj in -r..=*r {
+                        let inside = match self {
+                            Neighbourhood::VonNeumann(_) => di.abs() + dj.abs() <= *r,
+                            _ => true,
+                        };
+                        if inside && (di, dj) != (0, 0) {
+                            offsets.push((di, dj));
+                        }
+                    }
+                }
+                offsets
+            }
+            Neighbourhood::Custom(offsets) => offsets.clone(),
+        }
+    }
+}
 
-    for (di, dj) in directions {
-        let ni = start_i + di as i32;
-        let nj = start_j + dj as i32;
+#[derive(Clone, Debug)]
+struct Rules {
+    threshold: u32,
+    offsets: Vec<(isize, isize)>,
+    wrap: bool,
+}
 
-        if ni >= 0 && nj >= 0 {
-            set.insert((ni as usize, nj as usize));
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            threshold: 4,
+            offsets: Neighbourhood::Moore(1).offsets(),
+            wrap: false,
         }
     }
-    set
 }
 
-type AdjList = HashMap<(usize, usize), HashSet<(usize, usize)>>;
+impl Rules {
+    fn from_options(options: &Options) -> Result<Self, String> {
+        let default = Rules::default();
+        let neighbourhood: Option<Neighbourhood> = options.get("neighbourhood")?;
+        Ok(Rules {
+            threshold: options.get_or("threshold", default.threshold)?,
+            offsets: neighbourhood.map_or(default.offsets, |n| n.offsets()),
+            wrap: options.flag("wrap"),
+        })
+    }
+}
 
 #[derive(Debug)]
 struct Grid {
-    n: usize,
-    m: usize,
-    papers: AdjList,
+    width: usize,
+    height: usize,
+    papers: Vec<bool>,
+    counts: Vec<u32>,
+    rules: Rules,
 }
 
 impl Grid {
+    fn from_str(s: &str) -> Result<Self, String> {
+        Grid::with_rules(s, Rules::default(), false)
+    }
 
-    fn from_str(s: &str) -> Self {
-        let mut papers_set = HashSet::new();
-        let mut n = 0;
-        let mut m = 0;
-        for (i, line) in s.lines().enumerate() {
-            n += 1;
-            for (j, c) in line.char_indices() {
-                if c == '@' {
-                    papers_set.insert((i, j));
-                }
-                m += 1;
+    fn with_rules(s: &str, rules: Rules, ragged: bool) -> Result<Self, String> {
+        let (width, height) = grid_size(s, ragged)?;
+        let lines: Vec<&str> = s.lines().take(height).collect();
+        let mut papers = vec![false; width * height];
+        for (i, line) in lines.iter().enumerate() {
+            for (j, c) in line.chars().enumerate() {
+                papers[i * width + j] = c == '@';
             }
         }
-        n += 1;
-        m += 1;
+        let mut grid = Grid {
+            width,
+            height,
+            papers,
+            counts: vec![0; width * height],
+            rules,
+        };
+        grid.recount();
+        Ok(grid)
+    }
 
-        let mut papers: AdjList = HashMap::with_capacity(papers_set.len());
-        for paper in papers_set.iter() {
-            let neighbours: HashSet<(usize, usize)> = neighbours(paper)
-                .iter()
-                .filter(|p| papers_set.contains(p))
-                .cloned()
-                .collect();
-            papers.insert(*paper, neighbours);
+    fn recount(&mut self) {
+        for index in 0..self.papers.len() {
+            self.counts[index] = self.neighbours(index).filter(|&n| self.papers[n]).count() as u32;
+        }
+    }
+
+    fn shift(&self, index: usize, (di, dj): (isize, isize)) -> Option<usize> {
+        let (i, j) = (index / self.width, index % self.width);
+        if self.rules.wrap {
+            let ni = (i as isize + di).rem_euclid(self.height as isize) as usize;
+            let nj = (j as isize + dj).rem_euclid(self.width as isize) as usize;
+            return Some(ni * self.width + nj);
         }
-        Grid { n, m, papers }
+        let ni = i.checked_add_signed(di).filter(|&ni| ni < self.height)?;
+        let nj = j.checked_add_signed(dj).filter(|&nj| nj < self.width)?;
+        Some(ni * self.width + nj)
     }
 
-    fn removable(&self) -> HashSet<(usize, usize)> {
-        self.papers
+    fn neighbours(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+        self.rules
+            .offsets
             .iter()
-            .filter(|&(_, v)| v.len() < 4)
-            .map(|(k, _)| *k)
+            .filter_map(move |&offset| self.shift(index, offset))
+    }
+
+    fn dependents(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+        self.rules
+            .offsets
+            .iter()
+            .filter_map(move |&(di, dj)| self.shift(index, (-di, -dj)))
+    }
+
+    fn is_removable(&self, index: usize) -> bool {
+        self.papers[index] && self.counts[index] < self.rules.threshold
+    }
+
+    fn removable(&self) -> Vec<usize> {
+        (0..self.papers.len())
+            .filter(|&index| self.is_removable(index))
             .collect()
     }
 
-    fn remove(&mut self, paper: &(usize, usize)) {
-        if let Some(neighbours) = self.papers.get(paper).cloned() {
-            for neighbour in neighbours.iter() {
-                if let Some(e) = self.papers.get_mut(neighbour) {
-                    e.remove(paper);
+    fn peel(&mut self) -> Vec<Vec<usize>> {
+        self.peel_traced(&Tracer::default())
+    }
+
+    fn peel_traced(&mut self, tracer: &Tracer) -> Vec<Vec<usize>> {
+        let mut queued = vec![false; self.papers.len()];
+        let mut wave = self.removable();
+        for &index in wave.iter() {
+            queued[index] = true;
+        }
+        let mut waves = vec![];
+        while !wave.is_empty() {
+            let mut next = vec![];
+            for &index in wave.iter() {
+                self.papers[index] = false;
+            }
+            for &index in wave.iter() {
+                for n in self.dependents(index).collect::<Vec<_>>() {
+                    self.counts[n] -= 1;
+                    if !queued[n] && self.is_removable(n) {
+                        queued[n] = true;
+                        next.push(n);
+                    }
                 }
             }
-            self.papers.remove(paper);
+            trace!(
+                tracer,
+                "day04.wave",
+                wave = waves.len() + 1,
+                removed = wave.len(),
+                queued = next.len(),
+                remaining = self.papers.iter().filter(|&&p| p).count()
+            );
+            waves.push(wave);
+            wave = next;
         }
+        waves
     }
 }
 
 impl fmt::Display for Grid {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for i in 0..self.n {
-            for j in 0..self.m {
-                let c = if self.papers.contains_key(&(i, j)) {
-                    '@'
-                } else {
-                    '.'
-                };
-                write!(f, "{c}")?;
+        for row in self.papers.chunks(self.width.max(1)) {
+            for &paper in row {
+                write!(f, "{}", if paper { '@' } else { '.' })?;
             }
             writeln!(f)?;
         }
@@ -108,26 +228,131 @@ impl fmt::Display for Grid {
     }
 }
 
+const WAVE_SYMBOLS: &[u8] = b"123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+fn wave_colour(wave: usize, waves: usize) -> Rgb {
+    if waves > 1 {
+        gradient(wave as f64 / (waves - 1) as f64)
+    } else {
+        gradient(0.0)
+    }
+}
+
+struct Animation {
+    width: usize,
+    height: usize,
+    initial: Vec<bool>,
+    removed_in: Vec<Option<usize>>,
+    waves: usize,
+}
+
+impl Animation {
+    fn record(grid: &mut Grid) -> Self {
+        let initial = grid.papers.clone();
+        let waves = grid.peel();
+        let mut removed_in = vec![None; initial.len()];
+        for (w, wave) in waves.iter().enumerate() {
+            for &index in wave.iter() {
+                removed_in[index] = Some(w);
+            }
+        }
+        Animation {
+            width: grid.width,
+            height: grid.height,
+            initial,
+            removed_in,
+            waves: waves.len(),
+        }
+    }
+
+    fn frames(&self) -> usize {
+        self.waves + 1
+    }
+
+    fn cell(&self, index: usize, frame: usize) -> Option<Option<usize>> {
+        if !self.initial[index] {
+            return None;
+        }
+        Some(self.removed_in[index].filter(|&w| w < frame))
+    }
+
+    fn symbol(&self, index: usize, frame: usize) -> char {
+        match self.cell(index, frame) {
+            None => '.',
+            Some(None) => '@',
+            Some(Some(w)) => WAVE_SYMBOLS.get(w).map_or('#', |&b| b as char),
+        }
+    }
+
+    fn frame(&self, frame: usize) -> Frame {
+        Frame::from_fn(self.width, self.height, |i, j| {
+            let index = i * self.width + j;
+            let symbol = self.symbol(index, frame);
+            match self.cell(index, frame) {
+                None => Pixel::plain(symbol, (20, 20, 20)),
+                Some(None) => Pixel::plain(symbol, (240, 240, 240)),
+                Some(Some(wave)) => Pixel::coloured(symbol, wave_colour(wave, self.waves)),
+            }
+        })
+    }
+}
+
 impl AdventDay for Day04 {
     fn solve_part1(&self, input: &str) -> String {
-        let grid = Grid::from_str(input);
+        let grid = Grid::from_str(input).unwrap();
         grid.removable().len().to_string()
     }
     fn solve_part2(&self, input: &str) -> String {
-        let mut out = 0;
-        let mut grid = Grid::from_str(input);
-        let mut removable = grid.removable();
-        loop {
-            if removable.is_empty() {
-                break;
+        let mut grid = Grid::from_str(input).unwrap();
+        grid.peel()
+            .iter()
+            .map(|w| w.len())
+            .sum::<usize>()
+            .to_string()
+    }
+    fn options(&self) -> &'static [&'static str] {
+        &["threshold", "neighbourhood", "wrap", "ragged", "waves"]
+    }
+
+    fn retired_options(&self) -> &'static [(&'static str, &'static str)] {
+        const HINT: &str = "was replaced by --visualize terminal|flipbook|ppm|pgm with --frames-dir, --delay and --scale";
+        &[
+            ("animate", HINT),
+            ("out", HINT),
+            ("delay", HINT),
+            ("scale", HINT),
+        ]
+    }
+
+    fn solve_with(&self, part: Part, input: &str, options: &Options) -> Result<String, String> {
+        let rules = Rules::from_options(options)?;
+        let ragged = options.flag("ragged");
+        let mut grid = Grid::with_rules(input, rules.clone(), ragged)?;
+        if options.visualizer().enabled() {
+            let animation = Animation::record(&mut grid);
+            for frame in 0..animation.frames() {
+                options
+                    .visualizer()
+                    .show("day04", &animation.frame(frame))?;
             }
-            out += removable.len();
-            for r in removable {
-                grid.remove(&r);
+            grid = Grid::with_rules(input, rules, ragged)?;
+        }
+        let waves = grid.peel_traced(options.tracer());
+        let mut total = 0;
+        for (w, wave) in waves.iter().enumerate() {
+            total += wave.len();
+            if options.flag("waves") {
+                println!(
+                    "wave {:>3}: removed {:>5} | total {total}",
+                    w + 1,
+                    wave.len()
+                );
             }
-            removable = grid.removable();
         }
-        out.to_string()
+        Ok(match part {
+            Part::One => waves.first().map_or(0, |w| w.len()).to_string(),
+            Part::Two => total.to_string(),
+        })
     }
 }
 
@@ -150,4 +375,107 @@ mod tests {
         let result = day.solve_part2(INPUT);
         assert_eq!(result, "43");
     }
+
+    fn rescan_waves(grid: &mut Grid) -> Vec<usize> {
+        let mut waves = vec![];
+        loop {
+            let removable = grid.removable();
+            if removable.is_empty() {
+                return waves;
+            }
+            waves.push(removable.len());
+            for &index in removable.iter() {
+                grid.papers[index] = false;
+            }
+            grid.recount();
+        }
+    }
+
+    #[test]
+    fn peel_matches_full_rescans() {
+        let mut grid = Grid::from_str(INPUT).unwrap();
+        let waves: Vec<usize> = grid.peel().iter().map(|w| w.len()).collect();
+        assert_eq!(waves, rescan_waves(&mut Grid::from_str(INPUT).unwrap()));
+        assert_eq!(waves, vec![13, 12, 7, 5, 2, 1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn configurable_rules_match_full_rescans() {
+        let variants = [
+            "neighbourhood=von-neumann",
+            "neighbourhood=moore:2 threshold=10",
+            "neighbourhood=custom:0,1;1,0;1,1 threshold=2",
+            "neighbourhood=von-neumann:2 threshold=6 wrap",
+            "wrap",
+        ];
+        for variant in variants {
+            let pairs: Vec<String> = variant.split(' ').map(str::to_string).collect();
+            let rules = Rules::from_options(&Options::parse(&pairs).unwrap()).unwrap();
+            let mut grid = Grid::with_rules(INPUT, rules.clone(), false).unwrap();
+            let waves: Vec<usize> = grid.peel().iter().map(|w| w.len()).collect();
+            assert_eq!(
+                waves,
+                rescan_waves(&mut Grid::with_rules(INPUT, rules, false).unwrap()),
+                "{variant}"
+            );
+        }
+        assert_eq!(Neighbourhood::VonNeumann(1).offsets().len(), 4);
+        assert_eq!(Neighbourhood::Moore(2).offsets().len(), 24);
+        assert!("hex".parse::<Neighbourhood>().is_err());
+    }
+
+    #[test]
+    fn animation_frames_mark_removal_waves() {
+        let animation = Animation::record(&mut Grid::from_str(INPUT).unwrap());
+        assert_eq!(animation.frames(), 10);
+        assert_eq!(
+            animation.frame(0).text(),
+            Grid::from_str(INPUT).unwrap().to_string()
+        );
+        let last = animation.frame(animation.waves).text();
+        assert_eq!(last.lines().next(), Some("..11.1121."));
+        assert_eq!(
+            last.matches('@').count(),
+            Grid::from_str(INPUT)
+                .unwrap()
+                .papers
+                .iter()
+                .filter(|&&p| p)
+                .count()
+                - 43
+        );
+        let image = animation.frame(1).image(2, true);
+        assert!(image.starts_with(b"P5\n20 20\n255\n"));
+        assert_eq!(image.len(), "P5\n20 20\n255\n".len() + 400);
+        let options = Options::parse(&["animate=text".to_string()]).unwrap();
+        assert!(
+            options
+                .check_retired(Day04.retired_options())
+                .unwrap_err()
+                .starts_with("Option 'animate' was replaced by --visualize")
+        );
+        let options = Options::parse(&["thresh=3".to_string()]).unwrap();
+        assert_eq!(options.check_retired(Day04.retired_options()), Ok(()));
+        assert_eq!(
+            options.check(Day04.options()),
+            Err("Unknown option 'thresh', expected one of threshold, neighbourhood, wrap, ragged, waves".to_string())
+        );
+    }
+
+    #[test]
+    fn grid_dimensions_non_square_and_ragged() {
+        let grid = Grid::from_str("@@@@@\n@...@\n").unwrap();
+        assert_eq!((grid.width, grid.height), (5, 2));
+        assert_eq!(grid.to_string(), "@@@@@\n@...@\n");
+        assert_eq!(grid.removable().len(), 7);
+
+        let ragged = "@@@\n@\n@@";
+        assert_eq!(
+            Grid::from_str(ragged).unwrap_err(),
+            "Row 2 has 1 columns but row 1 has 3"
+        );
+        let grid = Grid::with_rules(ragged, Rules::default(), true).unwrap();
+        assert_eq!((grid.width, grid.height), (3, 3));
+        assert_eq!(grid.to_string(), "@@@\n@..\n@@.\n");
+    }
 }
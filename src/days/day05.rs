@@ -1,11 +1,19 @@
-use crate::days::AdventDay;
-use std::collections::BTreeMap;
+use crate::days::{AdventDay, Options, Part};
+use std::io::BufRead;
 
 pub struct Day05;
 
+// The ranges end at the first blank or whitespace-only line, the same rule
+// `solve_stream` applies line by line.
 fn parse_input(input: &str) -> (&str, &str) {
-    let mut parts = input.split("\n\n");
-    (parts.next().unwrap(), parts.next().unwrap())
+    let mut offset = 0;
+    for line in input.split_inclusive('\n') {
+        if line.trim().is_empty() {
+            return (&input[..offset], &input[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+    (input, "")
 }
 
 type Range = (usize, usize);
@@ -19,20 +27,46 @@ fn merge_ranges(r1: Range, r2: Range) -> Vec<Range> {
     }
 }
 
+fn parse_range(line: &str) -> Result<Range, String> {
+    let invalid = || format!("'{line}' is not a valid range");
+    let (lb, ub) = line.trim().split_once('-').ok_or_else(invalid)?;
+    let lb = lb.parse::<usize>().map_err(|_| invalid())?;
+    let ub = ub.parse::<usize>().map_err(|_| invalid())?;
+    if lb > ub {
+        return Err(invalid());
+    }
+    Ok((lb, ub))
+}
+
+#[derive(Debug, PartialEq)]
+struct Match {
+    index: usize,
+    range: Range,
+}
+
+fn print_match(item: usize, m: &Option<Match>) {
+    match m {
+        Some(Match { index, range }) => {
+            println!("{item}: fresh in range #{index} {}-{}", range.0, range.1)
+        }
+        None => println!("{item}: spoiled"),
+    }
+}
+
 #[derive(Debug)]
 struct Database {
-    map: BTreeMap<usize, usize>,
+    ranges: Vec<Range>,
+    original: Vec<Range>,
 }
 
 impl Database {
-    fn from_str(s: &str) -> Self {
-        let mut ranges = vec![];
-        for line in s.lines().map(|l| l.trim()) {
-            let mut lb_ub = line.split("-").filter_map(|n| n.parse::<usize>().ok());
-            if let (Some(lb), Some(ub)) = (lb_ub.next(), lb_ub.next()) {
-                ranges.push((lb, ub));
-            }
-        }
+    fn from_str(s: &str) -> Result<Self, String> {
+        let ranges = s.lines().map(parse_range).collect::<Result<_, _>>()?;
+        Ok(Database::from_ranges(ranges))
+    }
+
+    fn from_ranges(mut ranges: Vec<Range>) -> Self {
+        let original = ranges.clone();
         ranges.sort();
         let mut stack = vec![];
         for rng in ranges.into_iter() {
@@ -43,33 +77,169 @@ impl Database {
                 stack.extend(merge_ranges(last, rng));
             }
         }
-
-        let mut map = BTreeMap::new();
-        for (lb, ub) in stack.into_iter() {
-            map.insert(lb, ub);
+        Database {
+            ranges: stack,
+            original,
         }
+    }
+
+    fn lookup(&self, item: usize) -> Option<Match> {
+        let index = self
+            .ranges
+            .partition_point(|&(lb, _)| lb <= item)
+            .checked_sub(1)?;
+        let range = self.ranges[index];
+        (range.1 >= item).then_some(Match { index, range })
+    }
 
-        Database { map }
+    fn lookup_sorted(&self, items: &[usize]) -> Vec<Option<Match>> {
+        let mut index = 0;
+        items
+            .iter()
+            .map(|&item| {
+                while index < self.ranges.len() && self.ranges[index].1 < item {
+                    index += 1;
+                }
+                self.ranges
+                    .get(index)
+                    .filter(|range| range.0 <= item)
+                    .map(|&range| Match { index, range })
+            })
+            .collect()
     }
 
     fn is_fresh(&self, item: usize) -> bool {
-        self.map.range(..=item).any(|(_, &v)| v >= item)
+        self.lookup(item).is_some()
     }
 
     fn how_many_fresh(&self) -> usize {
-        self.map.iter().map(|(&k, &v)| v - k + 1).sum()
+        self.ranges.iter().map(|&(k, v)| v - k + 1).sum()
+    }
+
+    fn fresh_in_window(&self, lo: usize, hi: usize) -> usize {
+        if lo > hi {
+            return 0;
+        }
+        let first = self.ranges.partition_point(|&(_, ub)| ub < lo);
+        self.ranges[first..]
+            .iter()
+            .take_while(|&&(lb, _)| lb <= hi)
+            .map(|&(lb, ub)| ub.min(hi) - lb.max(lo) + 1)
+            .sum()
+    }
+
+    fn gaps(&self) -> Vec<Range> {
+        self.ranges
+            .windows(2)
+            .filter(|w| w[0].1 + 1 < w[1].0)
+            .map(|w| (w[0].1 + 1, w[1].0 - 1))
+            .collect()
+    }
+
+    fn nearest_fresh(&self, item: usize) -> Option<usize> {
+        if self.is_fresh(item) {
+            return Some(item);
+        }
+        let next = self.ranges.partition_point(|&(lb, _)| lb <= item);
+        let below = next.checked_sub(1).map(|i| self.ranges[i].1);
+        let above = self.ranges.get(next).map(|r| r.0);
+        match (below, above) {
+            (Some(b), Some(a)) if a - item < item - b => Some(a),
+            (Some(b), _) => Some(b),
+            (None, a) => a,
+        }
+    }
+
+    fn coverage(&self) -> Vec<(Range, usize)> {
+        let mut events: Vec<(usize, isize)> = vec![];
+        for &(lb, ub) in self.original.iter() {
+            events.push((lb, 1));
+            if let Some(end) = ub.checked_add(1) {
+                events.push((end, -1));
+            }
+        }
+        events.sort_unstable();
+        let mut segments = vec![];
+        let mut depth = 0isize;
+        for (i, &(point, delta)) in events.iter().enumerate() {
+            depth += delta;
+            if let Some(&(next, _)) = events.get(i + 1)
+                && next > point
+                && depth > 0
+            {
+                segments.push(((point, next - 1), depth as usize));
+            }
+        }
+        segments
+    }
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum Day05Query {
+    /// Count fresh IDs between LO and HI inclusive
+    Window { lo: usize, hi: usize },
+    /// List the spoiled spans between merged ranges
+    Gaps,
+    /// Find the fresh ID closest to ITEM
+    Nearest { item: usize },
+    /// Show how many original ranges cover each span
+    Overlaps,
+    /// Print the merged ranges
+    Dump,
+}
+
+pub fn run_query(input: &str, query: &Day05Query) -> Result<String, String> {
+    let (db_str, _) = parse_input(input);
+    let db = Database::from_str(db_str)?;
+    let mut out = vec![];
+    match *query {
+        Day05Query::Window { lo, hi } => {
+            out.push(format!(
+                "{} fresh IDs in {lo}-{hi}",
+                db.fresh_in_window(lo, hi)
+            ));
+        }
+        Day05Query::Gaps => {
+            for (lb, ub) in db.gaps() {
+                out.push(format!("{lb}-{ub} ({} spoiled)", ub - lb + 1));
+            }
+        }
+        Day05Query::Nearest { item } => match db.nearest_fresh(item) {
+            Some(fresh) => out.push(format!(
+                "nearest fresh ID to {item} is {fresh} (distance {})",
+                fresh.abs_diff(item)
+            )),
+            None => out.push("database has no fresh IDs".to_string()),
+        },
+        Day05Query::Overlaps => {
+            let segments = db.coverage();
+            let max = segments.iter().map(|&(_, d)| d).max().unwrap_or(0);
+            out.push(format!("max depth {max}"));
+            for ((lb, ub), depth) in segments {
+                out.push(format!("{lb}-{ub} covered by {depth}"));
+            }
+        }
+        Day05Query::Dump => {
+            for (i, (lb, ub)) in db.ranges.iter().enumerate() {
+                out.push(format!("#{i} {lb}-{ub} ({} IDs)", ub - lb + 1));
+            }
+        }
     }
+    Ok(out.join("\n"))
+}
+
+fn parse_items(items_str: &str) -> Vec<usize> {
+    items_str
+        .lines()
+        .filter_map(|l| l.trim().parse::<usize>().ok())
+        .collect()
 }
 
 impl AdventDay for Day05 {
     fn solve_part1(&self, input: &str) -> String {
         let (db_str, items_str) = parse_input(input);
-        let db = Database::from_str(db_str);
-        let items: Vec<usize> = items_str
-            .lines()
-            .filter_map(|l| l.trim().parse::<usize>().ok())
-            .collect();
-        items
+        let db = Database::from_str(db_str).unwrap();
+        parse_items(items_str)
             .into_iter()
             .filter(|&item| db.is_fresh(item))
             .count()
@@ -77,9 +247,75 @@ impl AdventDay for Day05 {
     }
     fn solve_part2(&self, input: &str) -> String {
         let (db_str, _) = parse_input(input);
-        let db = Database::from_str(db_str);
+        let db = Database::from_str(db_str).unwrap();
         db.how_many_fresh().to_string()
     }
+    fn options(&self) -> &'static [&'static str] {
+        &["batch", "matches"]
+    }
+    fn solve_with(&self, part: Part, input: &str, options: &Options) -> Result<String, String> {
+        let (db_str, items_str) = parse_input(input);
+        let db = Database::from_str(db_str)?;
+        if matches!(part, Part::Two) {
+            return Ok(db.how_many_fresh().to_string());
+        }
+        let mut items = parse_items(items_str);
+        let matches: Vec<Option<Match>> = if options.flag("batch") {
+            items.sort_unstable();
+            db.lookup_sorted(&items)
+        } else {
+            items.iter().map(|&item| db.lookup(item)).collect()
+        };
+        if options.flag("matches") {
+            for (&item, m) in items.iter().zip(matches.iter()) {
+                print_match(item, m);
+            }
+        }
+        Ok(matches.iter().flatten().count().to_string())
+    }
+    fn streams(&self) -> bool {
+        true
+    }
+    fn solve_stream(
+        &self,
+        part: Part,
+        reader: &mut dyn BufRead,
+        options: &Options,
+    ) -> Result<String, String> {
+        if options.flag("batch") {
+            let mut input = String::new();
+            reader
+                .read_to_string(&mut input)
+                .map_err(|e| e.to_string())?;
+            return self.solve_with(part, &input, options);
+        }
+        let mut lines = reader.lines();
+        let mut ranges = vec![];
+        for line in lines.by_ref() {
+            let line = line.map_err(|e| e.to_string())?;
+            if line.trim().is_empty() {
+                break;
+            }
+            ranges.push(parse_range(&line)?);
+        }
+        let db = Database::from_ranges(ranges);
+        if matches!(part, Part::Two) {
+            return Ok(db.how_many_fresh().to_string());
+        }
+        let mut out = 0;
+        for line in lines {
+            let line = line.map_err(|e| e.to_string())?;
+            let Ok(item) = line.trim().parse::<usize>() else {
+                continue;
+            };
+            let m = db.lookup(item);
+            if options.flag("matches") {
+                print_match(item, &m);
+            }
+            out += m.is_some() as usize;
+        }
+        Ok(out.to_string())
+    }
 }
 
 #[cfg(test)]
@@ -101,4 +337,81 @@ mod tests {
         let result = day.solve_part2(INPUT);
         assert_eq!(result, "14");
     }
+
+    #[test]
+    fn lookup_reports_matching_range() {
+        let (db_str, items_str) = parse_input(INPUT);
+        let db = Database::from_str(db_str).unwrap();
+        assert_eq!(db.ranges, vec![(3, 5), (10, 20)]);
+        assert_eq!(db.lookup(1), None);
+        assert_eq!(db.lookup(9), None);
+        assert_eq!(
+            db.lookup(17),
+            Some(Match {
+                index: 1,
+                range: (10, 20)
+            })
+        );
+        let mut items = parse_items(items_str);
+        items.sort_unstable();
+        let swept = db.lookup_sorted(&items);
+        let pointwise: Vec<Option<Match>> = items.iter().map(|&i| db.lookup(i)).collect();
+        assert_eq!(swept, pointwise);
+    }
+
+    #[test]
+    fn range_queries() {
+        let db = Database::from_str(parse_input(INPUT).0).unwrap();
+        assert_eq!(db.fresh_in_window(4, 12), 5);
+        assert_eq!(db.fresh_in_window(21, 30), 0);
+        assert_eq!(db.fresh_in_window(15, 12), 0);
+        assert_eq!(db.gaps(), vec![(6, 9)]);
+        assert_eq!(db.nearest_fresh(7), Some(5));
+        assert_eq!(db.nearest_fresh(8), Some(10));
+        assert_eq!(db.nearest_fresh(1), Some(3));
+        assert_eq!(db.nearest_fresh(25), Some(20));
+        assert_eq!(
+            db.coverage(),
+            vec![
+                ((3, 5), 1),
+                ((10, 11), 1),
+                ((12, 14), 2),
+                ((15, 15), 1),
+                ((16, 18), 2),
+                ((19, 20), 1)
+            ]
+        );
+        assert_eq!(
+            run_query(INPUT, &Day05Query::Dump).unwrap(),
+            "#0 3-5 (3 IDs)\n#1 10-20 (11 IDs)"
+        );
+    }
+
+    #[test]
+    fn streaming_matches_in_memory() {
+        let day = Day05;
+        for part in [Part::One, Part::Two] {
+            let mut reader = INPUT.as_bytes();
+            let streamed = day.solve_stream(part, &mut reader, &Options::default());
+            assert_eq!(streamed, Ok(day.solve(part, INPUT)));
+        }
+        let crlf = INPUT
+            .replace('\n', "\r\n")
+            .replacen("\r\n\r\n", "\r\n  \r\n", 1);
+        let batch = Options::parse(&["batch".to_string()]).unwrap();
+        for part in [Part::One, Part::Two] {
+            let mut reader = crlf.as_bytes();
+            let streamed = day.solve_stream(part, &mut reader, &Options::default());
+            assert_eq!(streamed, Ok(day.solve(part, INPUT)));
+            assert_eq!(day.solve_with(part, &crlf, &batch), streamed);
+        }
+        for (bad, line) in [("3-5\n10-x\n\n4\n", "10-x"), ("3-5\n10-5\n\n4\n", "10-5")] {
+            for part in [Part::One, Part::Two] {
+                let mut reader = bad.as_bytes();
+                let streamed = day.solve_stream(part, &mut reader, &Options::default());
+                assert_eq!(streamed, day.solve_with(part, bad, &Options::default()));
+                assert_eq!(streamed, Err(format!("'{line}' is not a valid range")));
+            }
+        }
+    }
 }
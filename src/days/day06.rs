@@ -1,33 +1,119 @@
+use std::f64::consts::LOG2_10;
 use std::vec;
 
-use crate::days::AdventDay;
+use crate::bigint::{BigUint, Natural, Overflow};
+use crate::days::{AdventDay, Options, Part};
+use crate::math::digit_count;
+use crate::visual::{Frame, Pixel, gradient};
 
 pub struct Day06;
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum Operation {
     Add,
     Mul,
+    Sub,
+    Div,
+    Min,
+    Max,
+    Concat,
+    Pow,
 }
 
 impl Operation {
-    fn from_str(s: &str) -> Self {
+    fn from_str(s: &str) -> Result<Self, String> {
         match s.trim() {
-            "+" => Operation::Add,
-            "*" => Operation::Mul,
-            _ => unreachable!(),
+            "+" => Ok(Operation::Add),
+            "*" => Ok(Operation::Mul),
+            "-" => Ok(Operation::Sub),
+            "/" => Ok(Operation::Div),
+            "<" => Ok(Operation::Min),
+            ">" => Ok(Operation::Max),
+            "|" => Ok(Operation::Concat),
+            "^" => Ok(Operation::Pow),
+            op => Err(format!("Unknown operator '{op}'")),
         }
     }
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            Operation::Add => "+",
+            Operation::Mul => "*",
+            Operation::Sub => "-",
+            Operation::Div => "/",
+            Operation::Min => "<",
+            Operation::Max => ">",
+            Operation::Concat => "|",
+            Operation::Pow => "^",
+        }
+    }
+
+    fn apply<T: Natural>(&self, acc: T, n: usize) -> Result<T, CalcError<T::Overflow>> {
+        let rhs = T::from_usize(n);
+        match self {
+            Operation::Add => acc.add_exact(&rhs).map_err(CalcError::Overflow),
+            Operation::Mul => acc.mul_exact(&rhs).map_err(CalcError::Overflow),
+            Operation::Sub => acc
+                .try_sub(&rhs)
+                .ok_or_else(|| CalcError::Invalid(format!("{acc} - {n} is negative"))),
+            Operation::Div => acc
+                .try_div(&rhs)
+                .ok_or_else(|| CalcError::Invalid(format!("{acc} / 0 is undefined"))),
+            Operation::Min => Ok(acc.min(rhs)),
+            Operation::Max => Ok(acc.max(rhs)),
+            Operation::Concat => {
+                let ten = T::from_usize(10);
+                (0..digit_count(n))
+                    .try_fold(acc, |acc, _| acc.mul_exact(&ten))
+                    .and_then(|acc| acc.add_exact(&rhs))
+                    .map_err(CalcError::Overflow)
+            }
+            Operation::Pow => {
+                if acc > T::one() && n as f64 * log2(&acc) > MAX_POW_BITS as f64 {
+                    return Err(CalcError::Invalid(format!(
+                        "{acc} ^ {n} has more than {MAX_POW_BITS} bits"
+                    )));
+                }
+                let exp = u32::try_from(n)
+                    .map_err(|_| CalcError::Invalid(format!("exponent {n} is too large")))?;
+                acc.pow_exact(exp).map_err(CalcError::Overflow)
+            }
+        }
+    }
+}
+
+// Powers are checked against this before they are computed, since the
+// `BigUint` fallback would otherwise grind through any exponent.
+const MAX_POW_BITS: usize = 1 << 17;
+
+fn log2<T: Natural>(n: &T) -> f64 {
+    let digits = n.to_string();
+    let (lead, rest) = digits.split_at(digits.len().min(15));
+    (lead.parse::<f64>().unwrap().log10() + rest.len() as f64) * LOG2_10
+}
+
+#[derive(Debug, PartialEq)]
+enum CalcError<O> {
+    Overflow(O),
+    Invalid(String),
+}
+
+#[derive(Debug, PartialEq)]
+struct Evaluation<T> {
+    results: Vec<T>,
+    total: T,
 }
 
-fn parse_input_part1(input: &str) -> (Vec<Operation>, Vec<Vec<usize>>) {
+type Worksheet = (Vec<Operation>, Vec<Vec<usize>>);
+
+fn parse_input_part1(input: &str) -> Result<Worksheet, String> {
     let mut lines: Vec<&str> = input.lines().collect();
     let operations: Vec<Operation> = lines
         .pop()
         .unwrap()
         .split_whitespace()
         .map(Operation::from_str)
-        .collect();
+        .collect::<Result<_, _>>()?;
     let interim: Vec<Vec<usize>> = lines
         .iter()
         .map(|&l| {
@@ -37,67 +123,274 @@ fn parse_input_part1(input: &str) -> (Vec<Operation>, Vec<Vec<usize>>) {
         })
         .collect();
     let n = interim.len();
-    let m = interim[0].len();
+    let m = interim.first().map_or(0, |row| row.len());
+    if let Some(i) = interim.iter().position(|row| row.len() != m) {
+        return Err(format!(
+            "Row {} has {} numbers but row 1 has {m}",
+            i + 1,
+            interim[i].len()
+        ));
+    }
+    if operations.len() != m {
+        return Err(format!(
+            "Worksheet has {} operators but {m} number columns",
+            operations.len()
+        ));
+    }
     let numbers: Vec<Vec<usize>> = (0..m)
         .map(|j| (0..n).map(|i| interim[i][j]).collect())
         .collect();
-    (operations, numbers)
+    Ok((operations, numbers))
 }
 
-fn parse_input_part2(input: &str) -> (Vec<Operation>, Vec<Vec<usize>>) {
-    let mut lines: Vec<&str> = input.lines().collect();
-    let operations: Vec<Operation> = lines
-        .pop()
-        .unwrap()
-        .split_whitespace()
-        .map(Operation::from_str)
-        .collect();
-    let chars: Vec<Vec<char>> = lines.iter().map(|&l| l.chars().collect()).collect();
-    let mut numbers: Vec<Vec<usize>> = vec![vec![]];
-    let n = chars.len();
-    let m = chars[0].len();
-    for j in 0..m {
-        let s: String = (0..n)
-            .filter_map(|i| {
-                if chars[i][j] != ' ' {
-                    Some(chars[i][j])
-                } else {
-                    None
-                }
-            })
-            .collect();
-        if s.is_empty() {
-            numbers.push(vec![]);
-            continue;
+#[derive(Debug, PartialEq)]
+struct Block {
+    start: usize,
+    end: usize,
+    operation: Operation,
+}
+
+fn pad_lines(input: &str) -> Vec<Vec<char>> {
+    let mut rows: Vec<Vec<char>> = input.lines().map(|l| l.chars().collect()).collect();
+    let width = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+    for row in rows.iter_mut() {
+        row.resize(width, ' ');
+    }
+    rows
+}
+
+fn segment(rows: &[Vec<char>]) -> Result<Vec<Block>, String> {
+    let (operator_row, digit_rows) = rows.split_last().ok_or("Worksheet is empty")?;
+    let width = operator_row.len();
+    let blank = |j: usize| digit_rows.iter().all(|row| row[j] == ' ');
+    let anchors: Vec<usize> = (0..width).filter(|&j| operator_row[j] != ' ').collect();
+    if let Some(j) = (0..anchors.first().copied().unwrap_or(width)).find(|&j| !blank(j)) {
+        return Err(format!(
+            "Digits in column {} precede the first operator",
+            j + 1
+        ));
+    }
+    let mut blocks = Vec::with_capacity(anchors.len());
+    for (k, &start) in anchors.iter().enumerate() {
+        let limit = anchors.get(k + 1).copied().unwrap_or(width);
+        let operation = Operation::from_str(&operator_row[start].to_string())
+            .map_err(|e| format!("{e} in column {}", start + 1))?;
+        let end = (start..limit)
+            .rev()
+            .find(|&j| !blank(j))
+            .map(|j| j + 1)
+            .ok_or(format!(
+                "Operator '{}' in column {} has no numbers above it",
+                operation.symbol(),
+                start + 1
+            ))?;
+        if let Some(gap) = (start..end).find(|&j| blank(j)) {
+            return Err(format!(
+                "Columns {}-{} hold two problems but only one operator '{}' in column {}, blank column {} splits them",
+                start + 1,
+                end,
+                operation.symbol(),
+                start + 1,
+                gap + 1
+            ));
         }
-        if let Some(last) = numbers.last_mut() {
-            last.push(s.parse::<usize>().unwrap());
+        blocks.push(Block {
+            start,
+            end,
+            operation,
+        });
+    }
+    Ok(blocks)
+}
+
+fn parse_input_part2(input: &str) -> Result<Worksheet, String> {
+    let rows = pad_lines(input);
+    let blocks = segment(&rows)?;
+    let digit_rows = &rows[..rows.len() - 1];
+    let mut numbers = Vec::with_capacity(blocks.len());
+    for block in blocks.iter() {
+        let mut problem = vec![];
+        for j in block.start..block.end {
+            let s: String = digit_rows
+                .iter()
+                .map(|row| row[j])
+                .filter(|&c| c != ' ')
+                .collect();
+            let n = s
+                .parse::<usize>()
+                .map_err(|_| format!("'{s}' in column {} is not a number", j + 1))?;
+            problem.push(n);
         }
+        numbers.push(problem);
     }
-    (operations, numbers)
+    Ok((blocks.into_iter().map(|b| b.operation).collect(), numbers))
 }
 
-fn calculate(operations: &[Operation], numbers: &[Vec<usize>]) -> usize {
-    let outputs: Vec<usize> = operations
-        .iter()
-        .enumerate()
-        .map(|(j, op)| match &op {
-            Operation::Add => numbers[j].iter().sum(),
-            Operation::Mul => numbers[j].iter().product(),
-        })
-        .collect();
-    outputs.into_iter().sum()
+// Every problem folds its numbers left to right in the order the parser
+// lists them, so `a b c` under `-` means `(a - b) - c`.
+fn calculate<T: Natural>(
+    operations: &[Operation],
+    numbers: &[Vec<usize>],
+) -> Result<Evaluation<T>, CalcError<T::Overflow>> {
+    let mut results = Vec::with_capacity(operations.len());
+    let mut total = T::zero();
+    for (j, op) in operations.iter().enumerate() {
+        let mut values = numbers[j].iter();
+        let first = match (values.next(), op) {
+            (Some(&n), _) => T::from_usize(n),
+            (None, Operation::Add) => T::zero(),
+            (None, Operation::Mul) => T::one(),
+            (None, _) => {
+                return Err(CalcError::Invalid(format!(
+                    "problem {} has no numbers",
+                    j + 1
+                )));
+            }
+        };
+        let output = values.try_fold(first, |acc, &n| op.apply(acc, n))?;
+        total = total.add_exact(&output).map_err(CalcError::Overflow)?;
+        results.push(output);
+    }
+    Ok(Evaluation { results, total })
+}
+
+fn report<T: Natural>(
+    evaluation: &Evaluation<T>,
+    operations: &[Operation],
+    numbers: &[Vec<usize>],
+) {
+    for (j, result) in evaluation.results.iter().enumerate() {
+        let terms: Vec<String> = numbers[j].iter().map(|n| n.to_string()).collect();
+        let symbol = format!(" {} ", operations[j].symbol());
+        println!("#{:<4} {} = {result}", j + 1, terms.join(&symbol));
+    }
+}
+
+fn calculate_promoting(
+    operations: &[Operation],
+    numbers: &[Vec<usize>],
+    problems: bool,
+) -> Result<String, String> {
+    match calculate::<usize>(operations, numbers) {
+        Ok(evaluation) => {
+            if problems {
+                report(&evaluation, operations, numbers);
+            }
+            Ok(evaluation.total.to_string())
+        }
+        Err(CalcError::Overflow(Overflow)) => {
+            let evaluation = match calculate::<BigUint>(operations, numbers) {
+                Ok(evaluation) => evaluation,
+                Err(CalcError::Invalid(msg)) => return Err(msg),
+            };
+            if problems {
+                report(&evaluation, operations, numbers);
+            }
+            Ok(evaluation.total.to_string())
+        }
+        Err(CalcError::Invalid(msg)) => Err(msg),
+    }
+}
+
+fn describe(operation: Operation, numbers: Option<&Vec<usize>>) -> String {
+    let Some(numbers) = numbers else {
+        return "-".to_string();
+    };
+    let terms: Vec<String> = numbers.iter().map(|n| n.to_string()).collect();
+    let result = match calculate::<BigUint>(&[operation], std::slice::from_ref(numbers)) {
+        Ok(evaluation) => evaluation.total.to_string(),
+        Err(CalcError::Invalid(msg)) => format!("error: {msg}"),
+    };
+    format!(
+        "{} = {result}",
+        terms.join(&format!(" {} ", operation.symbol()))
+    )
+}
+
+fn highlight_columns(rows: &[Vec<char>], start: usize, end: usize) -> String {
+    let mut out = String::new();
+    for row in rows.iter() {
+        let line: String = row[..start].iter().collect();
+        let marked: String = row[start..end].iter().collect();
+        let rest: String = row[end..].iter().collect();
+        out.push_str(&format!("    {line}\x1b[7m{marked}\x1b[0m{rest}\n"));
+    }
+    out
+}
+
+fn explain(input: &str) -> Result<String, String> {
+    let rows = pad_lines(input);
+    let blocks = segment(&rows)?;
+    let by_rows = parse_input_part1(input).map(|(_, numbers)| numbers);
+    let (_, by_columns) = parse_input_part2(input)?;
+    let mut out = String::new();
+    for (k, block) in blocks.iter().enumerate() {
+        out.push_str(&format!(
+            "Problem {} | columns {}-{} | operator '{}'\n",
+            k + 1,
+            block.start + 1,
+            block.end,
+            block.operation.symbol()
+        ));
+        let part1 = match &by_rows {
+            Ok(numbers) => describe(block.operation, numbers.get(k)),
+            Err(e) => format!("error: {e}"),
+        };
+        out.push_str(&format!("  part 1 (rows):    {part1}\n"));
+        out.push_str(&format!(
+            "  part 2 (columns): {}\n",
+            describe(block.operation, by_columns.get(k))
+        ));
+        out.push_str(&highlight_columns(&rows, block.start, block.end));
+    }
+    Ok(out)
+}
+
+/// The worksheet with every problem block coloured, marking `current`.
+fn worksheet_frame(rows: &[Vec<char>], blocks: &[Block], current: usize) -> Frame {
+    let width = rows.first().map_or(0, |r| r.len());
+    Frame::from_fn(width, rows.len(), |i, j| {
+        let c = rows[i][j];
+        match blocks.iter().position(|b| (b.start..b.end).contains(&j)) {
+            Some(k) if k == current => Pixel::bold(c, (255, 200, 0)),
+            Some(k) => Pixel::coloured(c, gradient(k as f64 / blocks.len() as f64)),
+            None => Pixel::plain(c, (20, 20, 20)),
+        }
+    })
 }
 
 impl AdventDay for Day06 {
     fn solve_part1(&self, input: &str) -> String {
-        let (operations, numbers) = parse_input_part1(input);
-        calculate(&operations, &numbers).to_string()
+        self.solve_with(Part::One, input, &Options::default())
+            .unwrap()
     }
 
     fn solve_part2(&self, input: &str) -> String {
-        let (operations, numbers) = parse_input_part2(input);
-        calculate(&operations, &numbers).to_string()
+        self.solve_with(Part::Two, input, &Options::default())
+            .unwrap()
+    }
+
+    fn options(&self) -> &'static [&'static str] {
+        &["explain", "problems"]
+    }
+
+    fn solve_with(&self, part: Part, input: &str, options: &Options) -> Result<String, String> {
+        if options.flag("explain") {
+            print!("{}", explain(input)?);
+        }
+        if options.visualizer().enabled() {
+            let rows = pad_lines(input);
+            let blocks = segment(&rows)?;
+            for k in 0..blocks.len() {
+                let frame = worksheet_frame(&rows, &blocks, k);
+                options.visualizer().show("day06", &frame)?;
+            }
+        }
+        let (operations, numbers) = match part {
+            Part::One => parse_input_part1(input)?,
+            Part::Two => parse_input_part2(input)?,
+        };
+        calculate_promoting(&operations, &numbers, options.flag("problems"))
     }
 }
 
@@ -120,4 +413,116 @@ mod tests {
         let result = day.solve_part2(INPUT);
         assert_eq!(result, "3263827");
     }
+
+    #[test]
+    fn solve_overflowing_product() {
+        let day = Day06;
+        let result = day.solve_part1("1000000000000 7\n1000000000000 8\n*             +\n");
+        assert_eq!(result, "1000000000000000000000015");
+    }
+
+    #[test]
+    fn extended_operators() {
+        let (operations, numbers) = parse_input_part1(
+            "100 2 7 9 12 2 7\n 30 5 3 4 34 3 3\n  2 3 5 6 5 2 2\n-   / < > | ^ -",
+        )
+        .unwrap();
+        let evaluation = calculate::<usize>(&operations, &numbers).unwrap();
+        assert_eq!(evaluation.results, vec![68, 0, 3, 9, 12345, 64, 2]);
+        assert_eq!(evaluation.total, 68 + 3 + 9 + 12345 + 64 + 2);
+
+        let (operations, numbers) = parse_input_part1("1\n2\n-").unwrap();
+        assert!(matches!(
+            calculate::<usize>(&operations, &numbers),
+            Err(CalcError::Invalid(_))
+        ));
+        let (operations, numbers) = parse_input_part1("2\n100\n^").unwrap();
+        assert_eq!(
+            calculate::<usize>(&operations, &numbers),
+            Err(CalcError::Overflow(Overflow))
+        );
+        assert_eq!(
+            calculate::<BigUint>(&operations, &numbers).unwrap().total,
+            BigUint::from(2u64).pow(100)
+        );
+        let (operations, numbers) = parse_input_part1("2\n4000000000\n^").unwrap();
+        assert_eq!(
+            calculate_promoting(&operations, &numbers, false),
+            Err("2 ^ 4000000000 has more than 131072 bits".to_string())
+        );
+        assert!(describe(Operation::Pow, Some(&vec![10, 40000])).starts_with("10 ^ 40000 = error"));
+        assert_eq!(
+            calculate::<usize>(&operations, &[vec![1, 4_000_000_000]])
+                .unwrap()
+                .total,
+            1
+        );
+        assert!(parse_input_part1("1\n2\n%").is_err());
+        assert_eq!(
+            parse_input_part1("1 2\n3 4\n+ * -"),
+            Err("Worksheet has 3 operators but 2 number columns".to_string())
+        );
+    }
+
+    #[test]
+    fn segment_worksheet_columns() {
+        let rows = pad_lines(INPUT);
+        let blocks = segment(&rows).unwrap();
+        let bounds: Vec<(usize, usize)> = blocks.iter().map(|b| (b.start, b.end)).collect();
+        assert_eq!(bounds, vec![(0, 3), (4, 7), (8, 11), (12, 15)]);
+
+        let ragged = "12 4\n3\n*  +";
+        assert_eq!(
+            parse_input_part2(ragged).unwrap().1,
+            vec![vec![13, 2], vec![4]]
+        );
+        assert_eq!(
+            segment(&pad_lines("12 4\n*")).unwrap_err(),
+            "Columns 1-4 hold two problems but only one operator '*' in column 1, blank column 3 splits them"
+        );
+        assert_eq!(
+            segment(&pad_lines(" 1  \n  * +")).unwrap_err(),
+            "Digits in column 2 precede the first operator"
+        );
+        assert_eq!(
+            segment(&pad_lines("1  \n* +")).unwrap_err(),
+            "Operator '+' in column 3 has no numbers above it"
+        );
+    }
+
+    #[test]
+    fn explain_shows_both_parsings() {
+        let text = explain(INPUT).unwrap();
+        let first: Vec<&str> = text.lines().take(3).collect();
+        assert_eq!(
+            first,
+            vec![
+                "Problem 1 | columns 1-3 | operator '*'",
+                "  part 1 (rows):    123 * 45 * 6 = 33210",
+                "  part 2 (columns): 1 * 24 * 356 = 8544",
+            ]
+        );
+        assert!(text.contains("    123 \x1b[7m328\x1b[0m  51 64 \n"));
+
+        let text = explain("12 4\n3\n*  +").unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines[1],
+            "  part 1 (rows):    error: Row 2 has 1 numbers but row 1 has 2"
+        );
+        assert_eq!(lines[2], "  part 2 (columns): 13 * 2 = 26");
+    }
+
+    #[test]
+    fn worksheet_frame_keeps_layout() {
+        let rows = pad_lines(INPUT);
+        let blocks = segment(&rows).unwrap();
+        let frame = worksheet_frame(&rows, &blocks, 1);
+        let text: String = rows
+            .iter()
+            .map(|r| r.iter().collect::<String>() + "\n")
+            .collect();
+        assert_eq!(frame.text(), text);
+        assert!(frame.ansi().contains("\x1b[1;38;2;255;200;0m3\x1b[0m"));
+    }
 }
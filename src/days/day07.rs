@@ -1,87 +1,658 @@
-use crate::days::AdventDay;
-use std::collections::{HashMap, HashSet};
+use crate::bigint::{BigUint, Natural};
+use crate::days::{AdventDay, Options, Part, grid_size};
+use crate::math::XorShift;
+use crate::trace::{Tracer, trace};
+use crate::visual::{Frame, Pixel};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 pub struct Day07;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Component {
+    Source,
+    Splitter,
+    Absorber,
+    DeflectLeft,
+    DeflectRight,
+    Funnel,
+    Wall,
+}
+
+impl FromStr for Component {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "source" => Ok(Component::Source),
+            "splitter" => Ok(Component::Splitter),
+            "absorber" => Ok(Component::Absorber),
+            "left" => Ok(Component::DeflectLeft),
+            "right" => Ok(Component::DeflectRight),
+            "funnel" => Ok(Component::Funnel),
+            "wall" => Ok(Component::Wall),
+            _ => Err(format!("'{s}' is not a manifold component")),
+        }
+    }
+}
+
+impl Component {
+    fn symbol(&self) -> char {
+        match self {
+            Component::Source => 'S',
+            Component::Splitter => '^',
+            Component::Absorber => 'x',
+            Component::DeflectLeft => '<',
+            Component::DeflectRight => '>',
+            Component::Funnel => 'V',
+            Component::Wall => '#',
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Mapping(HashMap<char, Component>);
+
+impl Default for Mapping {
+    fn default() -> Self {
+        use Component::*;
+        let all = [
+            Source,
+            Splitter,
+            Absorber,
+            DeflectLeft,
+            DeflectRight,
+            Funnel,
+            Wall,
+        ];
+        Mapping(all.into_iter().map(|c| (c.symbol(), c)).collect())
+    }
+}
+
+impl FromStr for Mapping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut mapping = Mapping::default();
+        for entry in s.split(',').filter(|e| !e.is_empty()) {
+            let (c, name) = entry
+                .split_once(':')
+                .ok_or(format!("'{entry}' is not a 'char:component' pair"))?;
+            let mut chars = c.chars();
+            let (Some(c), None) = (chars.next(), chars.next()) else {
+                return Err(format!("'{c}' is not a single character"));
+            };
+            if name == "empty" {
+                mapping.0.remove(&c);
+            } else {
+                mapping.0.insert(c, name.parse()?);
+            }
+        }
+        Ok(mapping)
+    }
+}
 
 struct Manifold {
-    start: (usize, usize),
-    splitters: HashSet<(usize, usize)>,
+    sources: Vec<(usize, usize)>,
+    cells: Vec<Option<Component>>,
+    splitters: Vec<u64>,
+    words: usize,
     length: usize,
     width: usize,
 }
 
 impl Manifold {
-    fn from_str(s: &str) -> Self {
-        let mut start = (0, 0);
-        let mut splitters: HashSet<(usize, usize)> = HashSet::new();
-        let mut length = 0;
-        let mut width = 0;
-        for (i, line) in s.lines().enumerate() {
-            length += 1;
-            for (j, c) in line.char_indices() {
-                width += 1;
-                if c == 'S' {
-                    start = (i, j);
-                } else if c == '^' {
-                    splitters.insert((i, j));
+    fn from_str(s: &str) -> Result<Self, String> {
+        Manifold::parse(s, false, &Mapping::default())
+    }
+
+    fn parse(s: &str, ragged: bool, mapping: &Mapping) -> Result<Self, String> {
+        let (width, length) = grid_size(s, ragged)?;
+        let mut sources = vec![];
+        let words = width.div_ceil(64);
+        let mut cells = vec![None; width * length];
+        let mut splitters = vec![0u64; words * length];
+        for (i, line) in s.lines().take(length).enumerate() {
+            for (j, c) in line.chars().enumerate() {
+                if let Some(&component) = mapping.0.get(&c) {
+                    match component {
+                        Component::Source => sources.push((i, j)),
+                        Component::Splitter => splitters[i * words + j / 64] |= 1 << (j % 64),
+                        _ => {}
+                    }
+                    cells[i * width + j] = Some(component);
                 }
             }
         }
-        width /= length;
-        Manifold {
-            start,
+        if sources.is_empty() {
+            return Err("Manifold has no source".to_string());
+        }
+        Ok(Manifold {
+            sources,
+            cells,
             splitters,
+            words,
             length,
             width,
+        })
+    }
+
+    fn cell(&self, i: usize, j: usize) -> Option<Component> {
+        if i < self.length && j < self.width {
+            self.cells[i * self.width + j]
+        } else {
+            None
+        }
+    }
+
+    fn is_splitter(&self, i: usize, j: usize) -> bool {
+        i < self.length && self.splitters[i * self.words + j / 64] & (1 << (j % 64)) != 0
+    }
+
+    fn splitters(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.length)
+            .flat_map(move |i| (0..self.width).map(move |j| (i, j)))
+            .filter(|&(i, j)| self.is_splitter(i, j))
+    }
+
+    // Components act on beams moving down into them. A funnel pulls in beams
+    // falling next to it, a wall destroys any beam a deflector or splitter
+    // moves sideways into it, and a beam moved sideways onto an absorber or
+    // splitter passes over it.
+    fn fall(&self, i: usize, j: usize) -> Option<usize> {
+        let side = |c: Option<usize>| {
+            c.filter(|&c| c < self.width && self.cell(i, c) != Some(Component::Wall))
+        };
+        match self.cell(i, j) {
+            Some(Component::Wall | Component::Absorber | Component::Splitter) => None,
+            Some(Component::DeflectLeft) => side(j.checked_sub(1)),
+            Some(Component::DeflectRight) => side(Some(j + 1)),
+            Some(Component::Funnel) => Some(j),
+            None | Some(Component::Source) => {
+                let funnel =
+                    |c: Option<usize>| c.filter(|&c| self.cell(i, c) == Some(Component::Funnel));
+                funnel(j.checked_sub(1)).or(funnel(Some(j + 1))).or(Some(j))
+            }
         }
     }
 
-    fn simulate(&self) -> (usize, usize) {
-        let mut curr: HashMap<usize, usize> = HashMap::from([(self.start.1, 1)]);
-        let mut splitters_count= 0;
+    fn split(&self, i: usize, j: usize) -> impl Iterator<Item = usize> + '_ {
+        [j.checked_sub(1), Some(j + 1)]
+            .into_iter()
+            .flatten()
+            .filter(move |&c| c < self.width && self.cell(i, c) != Some(Component::Wall))
+    }
+
+    fn simulate<T: Natural>(&self) -> Option<Simulation<T>> {
+        self.simulate_traced(&Tracer::default())
+    }
+
+    fn simulate_traced<T: Natural>(&self, tracer: &Tracer) -> Option<Simulation<T>> {
+        let zero = T::zero();
+        let mut curr = vec![zero.clone(); self.width];
+        let mut next = vec![zero.clone(); self.width];
+        let mut active = vec![false; self.width];
+        let mut landed = vec![false; self.width];
+        let mut beams: Vec<usize> = vec![];
+        let mut landing: Vec<usize> = vec![];
+        let mut hits: BTreeMap<(usize, usize), T> = BTreeMap::new();
+        let mut absorbed = T::zero();
         for i in 0..self.length {
-            let mut next: HashMap<usize, usize> = HashMap::new();
-            for (j, p) in curr.into_iter() {
-                let mut v = Vec::with_capacity(2);
-                if self.splitters.contains(&(i + 1, j)) {
-                    splitters_count += 1;
-                    if let Some(lj) = j.checked_sub(1) {
-                        v.push(lj);
+            for &(_, j) in self.sources.iter().filter(|s| s.0 == i) {
+                curr[j] = curr[j].try_add(&T::one())?;
+                if !active[j] {
+                    active[j] = true;
+                    beams.push(j);
+                }
+            }
+            for j in beams.drain(..) {
+                active[j] = false;
+                let p = std::mem::replace(&mut curr[j], zero.clone());
+                let mut land = |c: usize| {
+                    if !landed[c] {
+                        landed[c] = true;
+                        landing.push(c);
                     }
-                    if j < self.width - 1 {
-                        v.push(j + 1);
+                    next[c].try_add(&p).map(|v| next[c] = v)
+                };
+                if self.is_splitter(i + 1, j) {
+                    let e = hits.entry((i + 1, j)).or_insert_with(T::zero);
+                    *e = e.try_add(&p)?;
+                    for c in self.split(i + 1, j) {
+                        land(c)?;
                     }
-                } else {
-                    v.push(j)
+                } else if self.cell(i + 1, j) == Some(Component::Absorber) {
+                    absorbed = absorbed.try_add(&p)?;
+                } else if let Some(c) = self.fall(i + 1, j) {
+                    land(c)?;
                 }
-                for j in v.into_iter() {
-                    next.entry(j).and_modify(|e| *e += p).or_insert(p);
+            }
+            std::mem::swap(&mut curr, &mut next);
+            std::mem::swap(&mut active, &mut landed);
+            std::mem::swap(&mut beams, &mut landing);
+            trace!(
+                tracer,
+                "day07.row",
+                row = i + 1,
+                beams = beams.len(),
+                splits = hits.range((i + 1, 0)..(i + 2, 0)).count(),
+                absorbed = absorbed,
+                timelines = beams
+                    .iter()
+                    .try_fold(absorbed.clone(), |acc, &j| acc.try_add(&curr[j]))
+                    .map_or("overflow".to_string(), |t| t.to_string())
+            );
+        }
+        let exits: BTreeMap<usize, T> = beams
+            .into_iter()
+            .map(|j| (j, std::mem::replace(&mut curr[j], zero.clone())))
+            .collect();
+        let total = exits
+            .values()
+            .try_fold(absorbed.clone(), |acc, p| acc.try_add(p))?;
+        Some(Simulation {
+            hits,
+            exits,
+            absorbed,
+            total,
+        })
+    }
+
+    fn ways(&self) -> Vec<Vec<BigUint>> {
+        let last = vec![BigUint::one(); self.width];
+        self.count_back(self.length.saturating_sub(1), last, |_, _| true)
+    }
+
+    fn count_back(
+        &self,
+        last: usize,
+        base: Vec<BigUint>,
+        absorber: impl Fn(usize, usize) -> bool,
+    ) -> Vec<Vec<BigUint>> {
+        let mut ways = vec![vec![BigUint::zero(); self.width]; last + 1];
+        ways[last] = base;
+        for i in (0..last).rev() {
+            for j in 0..self.width {
+                ways[i][j] = match self.cell(i + 1, j) {
+                    Some(Component::Splitter) => {
+                        self.split(i + 1, j).map(|c| &ways[i + 1][c]).sum()
+                    }
+                    Some(Component::Absorber) if absorber(i + 1, j) => BigUint::one(),
+                    Some(Component::Absorber) => BigUint::zero(),
+                    _ => match self.fall(i + 1, j) {
+                        Some(c) => ways[i + 1][c].clone(),
+                        None => BigUint::zero(),
+                    },
+                };
+            }
+        }
+        ways
+    }
+
+    fn reach(
+        &self,
+        target: Target,
+        hits: &BTreeMap<(usize, usize), BigUint>,
+    ) -> Result<Reach, String> {
+        let (ti, tj) = match target {
+            Target::Exit(j) => (self.length - 1, j),
+            Target::Cell(i, j) => (i, j),
+        };
+        if ti >= self.length || tj >= self.width {
+            return Err(format!("{target} is outside the manifold"));
+        }
+        let mut base = vec![BigUint::zero(); self.width];
+        base[tj] = BigUint::one();
+        let absorbed = matches!(target, Target::Cell(..));
+        let ways = self.count_back(ti, base, |i, j| absorbed && (i, j) == (ti, tj));
+        let timelines = self
+            .sources
+            .iter()
+            .filter(|s| s.0 <= ti)
+            .map(|&(i, j)| &ways[i][j])
+            .sum();
+        let splitters = hits
+            .iter()
+            .filter(|&(&(i, j), _)| i <= ti && !ways[i - 1][j].is_zero())
+            .map(|(&(i, j), v)| ((i, j), v * &ways[i - 1][j]))
+            .collect();
+        let origins = (0..self.width).filter(|&j| !ways[0][j].is_zero()).collect();
+        Ok(Reach {
+            timelines,
+            splitters,
+            origins,
+        })
+    }
+
+    // Timelines are ordered by source, then by the turns taken at each
+    // splitter with left before right.
+    fn kth_timeline(&self, ways: &[Vec<BigUint>], mut k: BigUint) -> Option<Timeline> {
+        let mut start = None;
+        for &(i, j) in self.sources.iter() {
+            match k.checked_sub(&ways[i][j]) {
+                Some(rest) => k = rest,
+                None => {
+                    start = Some((i, j));
+                    break;
                 }
             }
-            curr = next;
         }
-        (splitters_count, curr.into_values().sum())
+        let (mut i, mut j) = start?;
+        let mut timeline = Timeline {
+            turns: vec![],
+            cells: vec![(i, j)],
+            exit: None,
+        };
+        while i + 1 < self.length {
+            match self.cell(i + 1, j) {
+                Some(Component::Splitter) => {
+                    let mut chosen = None;
+                    for c in self.split(i + 1, j) {
+                        match k.checked_sub(&ways[i + 1][c]) {
+                            Some(rest) => k = rest,
+                            None => {
+                                chosen = Some(c);
+                                break;
+                            }
+                        }
+                    }
+                    let c = chosen?;
+                    timeline
+                        .turns
+                        .push(if c < j { Turn::Left } else { Turn::Right });
+                    j = c;
+                }
+                Some(Component::Absorber) => {
+                    timeline.cells.push((i + 1, j));
+                    return Some(timeline);
+                }
+                _ => j = self.fall(i + 1, j)?,
+            }
+            i += 1;
+            timeline.cells.push((i, j));
+        }
+        timeline.exit = Some(j);
+        Some(timeline)
+    }
+
+    fn timelines(&self, count: usize) -> Vec<Timeline> {
+        let ways = self.ways();
+        (0..count)
+            .map_while(|k| self.kth_timeline(&ways, BigUint::from(k)))
+            .collect()
+    }
+
+    fn sample_timeline(&self, ways: &[Vec<BigUint>], rng: &mut XorShift) -> Option<Timeline> {
+        let total: BigUint = self.sources.iter().map(|&(i, j)| &ways[i][j]).sum();
+        if total.is_zero() {
+            return None;
+        }
+        let digits = total.to_string().len();
+        let k = loop {
+            let k: BigUint = (0..digits)
+                .map(|_| char::from(b'0' + (rng.next() % 10) as u8))
+                .collect::<String>()
+                .parse()
+                .unwrap();
+            if k < total {
+                break k;
+            }
+        };
+        self.kth_timeline(ways, k)
+    }
+
+    fn background(&self, i: usize, j: usize) -> Pixel {
+        match self.cell(i, j) {
+            Some(c) => Pixel::plain(c.symbol(), (200, 200, 200)),
+            None => Pixel::plain('.', (20, 20, 20)),
+        }
+    }
+
+    fn render_timeline(&self, timeline: &Timeline) -> Frame {
+        Frame::from_fn(self.width, self.length, |i, j| {
+            if timeline.cells.contains(&(i, j)) {
+                let symbol = self.cell(i, j).map_or('|', |c| c.symbol());
+                Pixel::bold(symbol, (255, 200, 0))
+            } else {
+                self.background(i, j)
+            }
+        })
+    }
+
+    fn unreached<T>(&self, simulation: &Simulation<T>) -> Vec<(usize, usize)> {
+        let mut unreached: Vec<(usize, usize)> = self
+            .splitters()
+            .filter(|s| !simulation.hits.contains_key(s))
+            .collect();
+        unreached.sort_unstable();
+        unreached
+    }
+
+    fn heatmap(&self, simulation: &Simulation<BigUint>) -> Frame {
+        let heat = |v: &BigUint| v.to_string().parse::<f64>().unwrap_or(f64::MAX).ln_1p();
+        let max = simulation.hits.values().map(heat).fold(0.0, f64::max);
+        Frame::from_fn(self.width, self.length, |i, j| {
+            if let Some(v) = simulation.hits.get(&(i, j)) {
+                let t = if max > 0.0 { heat(v) / max } else { 0.0 };
+                Pixel::bold('^', ((255.0 * t) as u8, 64, (255.0 * (1.0 - t)) as u8))
+            } else if self.is_splitter(i, j) {
+                Pixel::dim('^', (80, 80, 80))
+            } else {
+                self.background(i, j)
+            }
+        })
+    }
+
+    fn report(&self, simulation: &Simulation<BigUint>) -> String {
+        let mut out = vec![format!(
+            "{} of {} splitters reached, {} timelines",
+            simulation.hits.len(),
+            self.splitters().count(),
+            simulation.total
+        )];
+        out.push("splitter hits:".to_string());
+        for ((i, j), v) in simulation.hits.iter() {
+            out.push(format!("  ({i}, {j}): {v}"));
+        }
+        out.push("exit columns:".to_string());
+        for (j, v) in simulation.exits.iter() {
+            out.push(format!("  {j}: {v}"));
+        }
+        out.push(format!("absorbed: {}", simulation.absorbed));
+        out.push(format!("unreached: {:?}", self.unreached(simulation)));
+        out.join("\n")
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Simulation<T> {
+    hits: BTreeMap<(usize, usize), T>,
+    exits: BTreeMap<usize, T>,
+    absorbed: T,
+    total: T,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Target {
+    Exit(usize),
+    Cell(usize, usize),
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = |n: &str| {
+            n.trim()
+                .parse::<usize>()
+                .map_err(|_| format!("'{s}' is not an exit column or 'row,column' cell"))
+        };
+        match s.split_once(',') {
+            Some((i, j)) => Ok(Target::Cell(number(i)?, number(j)?)),
+            None => Ok(Target::Exit(number(s)?)),
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Target::Exit(j) => write!(f, "exit {j}"),
+            Target::Cell(i, j) => write!(f, "cell ({i}, {j})"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Reach {
+    timelines: BigUint,
+    splitters: BTreeMap<(usize, usize), BigUint>,
+    origins: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Turn {
+    Left,
+    Right,
+}
+
+#[derive(Debug, PartialEq)]
+struct Timeline {
+    turns: Vec<Turn>,
+    cells: Vec<(usize, usize)>,
+    exit: Option<usize>,
+}
+
+impl fmt::Display for Timeline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for turn in self.turns.iter() {
+            write!(f, "{}", if *turn == Turn::Left { 'L' } else { 'R' })?;
+        }
+        let (i, j) = self.cells.last().unwrap();
+        match self.exit {
+            Some(exit) => write!(f, " -> exit {exit}")?,
+            None => write!(f, " -> absorbed at ({i}, {j})")?,
+        }
+        let cells: Vec<String> = self
+            .cells
+            .iter()
+            .map(|(i, j)| format!("({i}, {j})"))
+            .collect();
+        write!(f, " via {}", cells.join(" "))
+    }
+}
+
+fn solve_manifold(manifold: &Manifold, part: Part, tracer: &Tracer) -> String {
+    let answer = |splits: usize, total: &dyn fmt::Display| match part {
+        Part::One => splits.to_string(),
+        Part::Two => total.to_string(),
+    };
+    match manifold.simulate_traced::<usize>(tracer) {
+        Some(simulation) => answer(simulation.hits.len(), &simulation.total),
+        None => {
+            trace!(tracer, "day07.promote", to = "BigUint");
+            let simulation = manifold.simulate_traced::<BigUint>(tracer).unwrap();
+            answer(simulation.hits.len(), &simulation.total)
+        }
     }
 }
 
 impl AdventDay for Day07 {
     fn solve_part1(&self, input: &str) -> String {
-        let manifold = Manifold::from_str(input);
-        let (splitters_count, _) = manifold.simulate();
-        splitters_count.to_string()
+        let manifold = Manifold::from_str(input).unwrap();
+        solve_manifold(&manifold, Part::One, &Tracer::default())
     }
 
     fn solve_part2(&self, input: &str) -> String {
-        let manifold = Manifold::from_str(input);
-        let (_, total_paths) = manifold.simulate();
-        total_paths.to_string()
+        let manifold = Manifold::from_str(input).unwrap();
+        solve_manifold(&manifold, Part::Two, &Tracer::default())
+    }
+
+    fn options(&self) -> &'static [&'static str] {
+        &[
+            "ragged",
+            "cells",
+            "report",
+            "heatmap",
+            "reach",
+            "timelines",
+            "timeline",
+            "sample",
+            "seed",
+        ]
+    }
+
+    fn solve_with(&self, part: Part, input: &str, options: &Options) -> Result<String, String> {
+        let mapping = options.get_or("cells", Mapping::default())?;
+        let manifold = Manifold::parse(input, options.flag("ragged"), &mapping)?;
+        let visualizer = options.visualizer();
+        let show = |name: &str, frame: Frame| {
+            if visualizer.enabled() {
+                visualizer.show(name, &frame)
+            } else {
+                print!("{}", frame.ansi());
+                Ok(())
+            }
+        };
+        let heatmap = options.flag("heatmap") || visualizer.enabled();
+        if options.flag("report") || heatmap {
+            let simulation = manifold.simulate::<BigUint>().unwrap();
+            if heatmap {
+                show("day07_heatmap", manifold.heatmap(&simulation))?;
+            }
+            if options.flag("report") {
+                println!("{}", manifold.report(&simulation));
+            }
+        }
+        if let Some(targets) = options.get::<String>("reach")? {
+            let hits = manifold.simulate::<BigUint>().unwrap().hits;
+            for target in targets.split(';') {
+                let target: Target = target.parse()?;
+                let reach = manifold.reach(target, &hits)?;
+                println!("{target}: {} timelines", reach.timelines);
+                println!("  origin columns: {:?}", reach.origins);
+                for ((i, j), v) in reach.splitters.iter() {
+                    println!("  splitter ({i}, {j}): {v}");
+                }
+            }
+        }
+        if let Some(count) = options.get::<usize>("timelines")? {
+            for timeline in manifold.timelines(count) {
+                println!("{timeline}");
+            }
+        }
+        if let Some(k) = options.get::<BigUint>("timeline")? {
+            let timeline = manifold
+                .kth_timeline(&manifold.ways(), k.clone())
+                .ok_or(format!("Manifold has no timeline #{k}"))?;
+            show("day07_timeline", manifold.render_timeline(&timeline))?;
+            println!("{timeline}");
+        }
+        if let Some(count) = options.get::<usize>("sample")? {
+            let seed = match options.get::<u64>("seed")? {
+                Some(seed) => seed,
+                None => SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(1, |d| d.as_nanos() as u64),
+            };
+            let (ways, mut rng) = (manifold.ways(), XorShift::new(seed));
+            for _ in 0..count {
+                if let Some(timeline) = manifold.sample_timeline(&ways, &mut rng) {
+                    show("day07_sample", manifold.render_timeline(&timeline))?;
+                    println!("{timeline}");
+                }
+            }
+        }
+        Ok(solve_manifold(&manifold, part, options.tracer()))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::{Duration, Instant};
 
     const INPUT: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/examples/day07.txt"));
 
@@ -98,4 +669,250 @@ mod tests {
         let result = day.solve_part2(INPUT);
         assert_eq!(result, "40");
     }
+
+    #[test]
+    fn manifold_dimensions_non_square_and_ragged() {
+        let manifold = Manifold::from_str("..S..\n.....\n..^..\n").unwrap();
+        assert_eq!((manifold.width, manifold.length), (5, 3));
+        assert_eq!(manifold.simulate::<usize>().unwrap().total, 2);
+
+        let ragged = "S..\n^\n...";
+        assert_eq!(
+            Manifold::from_str(ragged).err(),
+            Some("Row 2 has 1 columns but row 1 has 3".to_string())
+        );
+        let manifold = Manifold::parse(ragged, true, &Mapping::default()).unwrap();
+        assert_eq!((manifold.width, manifold.length), (3, 3));
+        assert_eq!(manifold.simulate::<usize>().unwrap().total, 1);
+        assert!(Manifold::from_str("...\n...").is_err());
+    }
+
+    #[test]
+    fn simulation_statistics() {
+        let manifold = Manifold::from_str(INPUT).unwrap();
+        let simulation = manifold.simulate::<usize>().unwrap();
+        assert_eq!(simulation.hits[&(2, 7)], 1);
+        assert_eq!(simulation.hits[&(4, 6)], 1);
+        assert_eq!(simulation.exits.values().sum::<usize>(), 40);
+        assert_eq!(
+            manifold.unreached(&simulation).len(),
+            manifold.splitters().count() - 21
+        );
+
+        let manifold = Manifold::from_str("..S..\n.....\n..^..\n.....\n^....").unwrap();
+        let simulation = manifold.simulate::<usize>().unwrap();
+        assert_eq!(simulation.exits, BTreeMap::from([(1, 1), (3, 1)]));
+        assert_eq!(manifold.unreached(&simulation), vec![(4, 0)]);
+    }
+
+    #[test]
+    fn extended_components() {
+        let manifold = Manifold::from_str("S...S\n>...<\n.V.x.\n.^...\n.....").unwrap();
+        let simulation = manifold.simulate::<usize>().unwrap();
+        assert_eq!(simulation.hits, BTreeMap::from([((3, 1), 1)]));
+        assert_eq!(simulation.absorbed, 1);
+        assert_eq!(simulation.exits, BTreeMap::from([(0, 1), (2, 1)]));
+        assert_eq!(simulation.total, 3);
+
+        let manifold = Manifold::from_str("..S..\n.....\n.#^..\n.....").unwrap();
+        let simulation = manifold.simulate::<usize>().unwrap();
+        assert_eq!(simulation.exits, BTreeMap::from([(3, 1)]));
+
+        let mapping: Mapping = "o:source,S:empty,*:splitter".parse().unwrap();
+        let manifold = Manifold::parse("..o..\n.....\n..*..\n", false, &mapping).unwrap();
+        assert_eq!(manifold.simulate::<usize>().unwrap().total, 2);
+        assert!(Manifold::parse("..S..", false, &mapping).is_err());
+        assert!("o:portal".parse::<Mapping>().is_err());
+    }
+
+    #[test]
+    fn timelines_in_lexicographic_order() {
+        let manifold = Manifold::from_str(INPUT).unwrap();
+        let ways = manifold.ways();
+        let start = manifold.sources[0];
+        assert_eq!(ways[start.0][start.1], BigUint::from(40usize));
+        let timelines = manifold.timelines(50);
+        assert_eq!(timelines.len(), 40);
+        assert!(timelines.windows(2).all(|w| w[0].turns < w[1].turns));
+        assert!(timelines[0].turns.iter().all(|&t| t == Turn::Left));
+        assert!(timelines[39].turns.iter().all(|&t| t == Turn::Right));
+        assert_eq!(timelines[0].cells.len(), manifold.length);
+        let exits = timelines.iter().filter(|t| t.exit == Some(6)).count();
+        let simulation = manifold.simulate::<usize>().unwrap();
+        assert_eq!(exits, simulation.exits[&6]);
+
+        let mut rng = XorShift::new(7);
+        for _ in 0..20 {
+            let sample = manifold.sample_timeline(&ways, &mut rng).unwrap();
+            assert!(timelines.contains(&sample));
+        }
+    }
+
+    fn simulate_sparse<T: Natural>(manifold: &Manifold) -> Option<Simulation<T>> {
+        let mut curr: HashMap<usize, T> = HashMap::new();
+        let mut hits: BTreeMap<(usize, usize), T> = BTreeMap::new();
+        let mut absorbed = T::zero();
+        for i in 0..manifold.length {
+            for &(_, j) in manifold.sources.iter().filter(|s| s.0 == i) {
+                let e = curr.entry(j).or_insert_with(T::zero);
+                *e = e.try_add(&T::one())?;
+            }
+            let mut next: HashMap<usize, T> = HashMap::new();
+            for (j, p) in curr.into_iter() {
+                let mut v = Vec::with_capacity(2);
+                match manifold.cell(i + 1, j) {
+                    Some(Component::Splitter) => {
+                        let e = hits.entry((i + 1, j)).or_insert_with(T::zero);
+                        *e = e.try_add(&p)?;
+                        v.extend(manifold.split(i + 1, j));
+                    }
+                    Some(Component::Absorber) => absorbed = absorbed.try_add(&p)?,
+                    _ => v.extend(manifold.fall(i + 1, j)),
+                }
+                for j in v.into_iter() {
+                    let e = next.entry(j).or_insert_with(T::zero);
+                    *e = e.try_add(&p)?;
+                }
+            }
+            curr = next;
+        }
+        let exits: BTreeMap<usize, T> = curr.into_iter().collect();
+        let total = exits
+            .values()
+            .try_fold(absorbed.clone(), |acc, p| acc.try_add(p))?;
+        Some(Simulation {
+            hits,
+            exits,
+            absorbed,
+            total,
+        })
+    }
+
+    fn random_manifold(rng: &mut XorShift, width: usize, length: usize, mixed: bool) -> String {
+        let mut rows = vec![];
+        for i in 0..length {
+            let row: String = (0..width)
+                .map(|j| match rng.next() % 40 {
+                    _ if i == 0 && j == width / 2 => 'S',
+                    0..6 if i % 2 == 0 => '^',
+                    _ if !mixed => '.',
+                    6 => 'x',
+                    7 => '<',
+                    8 => '>',
+                    9 => 'V',
+                    10 => '#',
+                    11 if i < 3 => 'S',
+                    _ => '.',
+                })
+                .collect();
+            rows.push(row);
+        }
+        rows.join("\n")
+    }
+
+    #[test]
+    fn dense_matches_sparse_simulation() {
+        let mut rng = XorShift::new(0x2545_f491_4f6c_dd1d);
+        for round in 0..40 {
+            let (width, length) = (1 + round * 7 % 150, 2 + round * 13 % 90);
+            let input = random_manifold(&mut rng, width, length, true);
+            let manifold = Manifold::from_str(&input).unwrap();
+            assert_eq!(
+                manifold.simulate::<BigUint>(),
+                simulate_sparse::<BigUint>(&manifold)
+            );
+            assert_eq!(
+                manifold.simulate::<usize>(),
+                simulate_sparse::<usize>(&manifold)
+            );
+        }
+    }
+
+    // cargo test --release simulation_timings -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn simulation_timings() {
+        fn time<R>(f: impl Fn() -> R) -> (R, Duration) {
+            let runs = 5;
+            let start = Instant::now();
+            for _ in 1..runs {
+                std::hint::black_box(f());
+            }
+            let out = f();
+            (out, start.elapsed() / runs)
+        }
+        let mut rng = XorShift::new(0x9e37_79b9_7f4a_7c15);
+        for (size, mixed) in [(141, false), (1000, false), (3000, false), (3000, true)] {
+            let input = random_manifold(&mut rng, size, size, mixed);
+            let manifold = Manifold::from_str(&input).unwrap();
+            let (dense, dense_big) = time(|| manifold.simulate::<BigUint>());
+            let (sparse, sparse_big) = time(|| simulate_sparse::<BigUint>(&manifold));
+            assert_eq!(dense, sparse);
+            let (dense, dense_usize) = time(|| manifold.simulate::<usize>());
+            let (sparse, sparse_usize) = time(|| simulate_sparse::<usize>(&manifold));
+            assert_eq!(dense, sparse);
+            let kind = if mixed { "mixed" } else { "splitter-only" };
+            let usize_note = if dense.is_none() { " (overflows)" } else { "" };
+            println!(
+                "{kind} {size}x{size}: BigUint {dense_big:?} vs {sparse_big:?} sparse, usize{usize_note} {dense_usize:?} vs {sparse_usize:?} sparse"
+            );
+        }
+    }
+
+    #[test]
+    fn reverse_reachability() {
+        let manifold = Manifold::from_str(INPUT).unwrap();
+        let simulation = manifold.simulate::<BigUint>().unwrap();
+        for (&j, count) in simulation.exits.iter() {
+            let reach = manifold.reach(Target::Exit(j), &simulation.hits).unwrap();
+            assert_eq!(&reach.timelines, count);
+        }
+        let reach = manifold.reach(Target::Exit(0), &simulation.hits).unwrap();
+        assert_eq!(reach.splitters.len(), 7);
+        assert!(reach.splitters.values().all(|v| *v == BigUint::one()));
+        assert_eq!(reach.origins, (0..=7).collect::<Vec<_>>());
+        let reach = manifold.reach(Target::Exit(1), &simulation.hits).unwrap();
+        assert!(reach.timelines.is_zero() && reach.splitters.is_empty());
+
+        let reach = manifold
+            .reach(Target::Cell(2, 6), &simulation.hits)
+            .unwrap();
+        assert_eq!(reach.timelines, BigUint::one());
+        assert_eq!(reach.splitters.keys().collect::<Vec<_>>(), vec![&(2, 7)]);
+        assert!(manifold.reach(Target::Exit(99), &simulation.hits).is_err());
+        assert_eq!("3,4".parse::<Target>(), Ok(Target::Cell(3, 4)));
+
+        let manifold = Manifold::from_str("..S..\n.....\n..^..\n.x...\n.....").unwrap();
+        let simulation = manifold.simulate::<BigUint>().unwrap();
+        let reach = manifold
+            .reach(Target::Cell(3, 1), &simulation.hits)
+            .unwrap();
+        assert_eq!(reach.timelines, BigUint::one());
+        assert_eq!(reach.origins, vec![1, 2]);
+
+        let manifold = Manifold::from_str("S.\n^x").unwrap();
+        let simulation = manifold.simulate::<BigUint>().unwrap();
+        assert_eq!(simulation.exits, BTreeMap::from([(1, BigUint::one())]));
+        let reach = manifold.reach(Target::Exit(1), &simulation.hits).unwrap();
+        assert_eq!(reach.timelines, BigUint::one());
+        assert_eq!(reach.origins, vec![0]);
+        assert_eq!(manifold.timelines(2).len(), 1);
+        assert_eq!(manifold.timelines(1)[0].exit, Some(1));
+    }
+
+    #[test]
+    fn reach_matches_simulated_exits() {
+        let mut rng = XorShift::new(0x5851_f42d_4c95_7f2d);
+        for round in 0..40 {
+            let (width, length) = (1 + round * 5 % 40, 2 + round * 7 % 30);
+            let input = random_manifold(&mut rng, width, length, true);
+            let manifold = Manifold::from_str(&input).unwrap();
+            let simulation = manifold.simulate::<BigUint>().unwrap();
+            for j in 0..width {
+                let reach = manifold.reach(Target::Exit(j), &simulation.hits).unwrap();
+                let expected = simulation.exits.get(&j).cloned().unwrap_or_default();
+                assert_eq!(reach.timelines, expected, "exit {j}\n{input}");
+            }
+        }
+    }
 }
@@ -0,0 +1,461 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+use std::ops::Add;
+
+pub type AdjList<N> = HashMap<N, HashSet<N>>;
+
+pub fn adjacent<N>(adj: &AdjList<N>) -> impl Fn(&N) -> Vec<N> + '_
+where
+    N: Clone + Eq + Hash,
+{
+    |n| {
+        adj.get(n)
+            .map(|set| set.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+fn rebuild_path<N>(parents: &HashMap<N, N>, goal: N) -> Vec<N>
+where
+    N: Clone + Eq + Hash,
+{
+    let mut path = vec![goal];
+    while let Some(parent) = parents.get(path.last().unwrap()) {
+        path.push(parent.clone());
+    }
+    path.reverse();
+    path
+}
+
+pub fn bfs<N, F, I>(start: N, mut neighbours: F) -> HashMap<N, usize>
+where
+    N: Clone + Eq + Hash,
+    F: FnMut(&N) -> I,
+    I: IntoIterator<Item = N>,
+{
+    let mut dist = HashMap::from([(start.clone(), 0)]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(node) = queue.pop_front() {
+        let d = dist[&node];
+        for next in neighbours(&node) {
+            if !dist.contains_key(&next) {
+                dist.insert(next.clone(), d + 1);
+                queue.push_back(next);
+            }
+        }
+    }
+    dist
+}
+
+pub fn bfs_path<N, F, G, I>(start: N, mut is_goal: G, mut neighbours: F) -> Option<Vec<N>>
+where
+    N: Clone + Eq + Hash,
+    F: FnMut(&N) -> I,
+    G: FnMut(&N) -> bool,
+    I: IntoIterator<Item = N>,
+{
+    let mut parents: HashMap<N, N> = HashMap::new();
+    let mut seen = HashSet::from([start.clone()]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(node) = queue.pop_front() {
+        if is_goal(&node) {
+            return Some(rebuild_path(&parents, node));
+        }
+        for next in neighbours(&node) {
+            if seen.insert(next.clone()) {
+                parents.insert(next.clone(), node.clone());
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+pub fn dfs<N, F, I>(start: N, mut neighbours: F) -> Vec<N>
+where
+    N: Clone + Eq + Hash,
+    F: FnMut(&N) -> I,
+    I: IntoIterator<Item = N>,
+{
+    let mut order = vec![];
+    let mut seen = HashSet::new();
+    let mut stack = vec![start];
+    while let Some(node) = stack.pop() {
+        if !seen.insert(node.clone()) {
+            continue;
+        }
+        let mut next: Vec<N> = neighbours(&node)
+            .into_iter()
+            .filter(|n| !seen.contains(n))
+            .collect();
+        next.reverse();
+        stack.extend(next);
+        order.push(node);
+    }
+    order
+}
+
+struct State<C> {
+    priority: C,
+    cost: C,
+    index: usize,
+}
+
+impl<C: Ord> PartialEq for State<C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<C: Ord> Eq for State<C> {}
+
+impl<C: Ord> PartialOrd for State<C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<C: Ord> Ord for State<C> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (&self.priority, &self.cost, self.index).cmp(&(&other.priority, &other.cost, other.index))
+    }
+}
+
+pub fn dijkstra<N, C, F, I>(start: N, mut neighbours: F) -> HashMap<N, C>
+where
+    N: Clone + Eq + Hash,
+    C: Copy + Ord + Default + Add<Output = C>,
+    F: FnMut(&N) -> I,
+    I: IntoIterator<Item = (N, C)>,
+{
+    let mut dist: HashMap<N, C> = HashMap::from([(start.clone(), C::default())]);
+    let mut nodes = vec![start];
+    let mut heap = BinaryHeap::from([Reverse(State {
+        priority: C::default(),
+        cost: C::default(),
+        index: 0,
+    })]);
+    while let Some(Reverse(State { cost, index, .. })) = heap.pop() {
+        let node = nodes[index].clone();
+        if dist.get(&node).is_some_and(|&d| d < cost) {
+            continue;
+        }
+        for (next, weight) in neighbours(&node) {
+            let next_cost = cost + weight;
+            if dist.get(&next).is_none_or(|&d| next_cost < d) {
+                dist.insert(next.clone(), next_cost);
+                nodes.push(next);
+                heap.push(Reverse(State {
+                    priority: next_cost,
+                    cost: next_cost,
+                    index: nodes.len() - 1,
+                }));
+            }
+        }
+    }
+    dist
+}
+
+pub fn astar<N, C, F, G, H, I>(
+    start: N,
+    mut is_goal: G,
+    mut neighbours: F,
+    mut heuristic: H,
+) -> Option<(C, Vec<N>)>
+where
+    N: Clone + Eq + Hash,
+    C: Copy + Ord + Default + Add<Output = C>,
+    F: FnMut(&N) -> I,
+    G: FnMut(&N) -> bool,
+    H: FnMut(&N) -> C,
+    I: IntoIterator<Item = (N, C)>,
+{
+    let mut dist: HashMap<N, C> = HashMap::from([(start.clone(), C::default())]);
+    let mut parents: HashMap<N, N> = HashMap::new();
+    let mut heap = BinaryHeap::from([Reverse(State {
+        priority: heuristic(&start),
+        cost: C::default(),
+        index: 0,
+    })]);
+    let mut nodes = vec![start];
+    while let Some(Reverse(State { cost, index, .. })) = heap.pop() {
+        let node = nodes[index].clone();
+        if dist.get(&node).is_some_and(|&d| d < cost) {
+            continue;
+        }
+        if is_goal(&node) {
+            return Some((cost, rebuild_path(&parents, node)));
+        }
+        for (next, weight) in neighbours(&node) {
+            let next_cost = cost + weight;
+            if dist.get(&next).is_none_or(|&d| next_cost < d) {
+                dist.insert(next.clone(), next_cost);
+                parents.insert(next.clone(), node.clone());
+                heap.push(Reverse(State {
+                    priority: next_cost + heuristic(&next),
+                    cost: next_cost,
+                    index: nodes.len(),
+                }));
+                nodes.push(next);
+            }
+        }
+    }
+    None
+}
+
+pub fn dijkstra_path<N, C, F, G, I>(start: N, is_goal: G, neighbours: F) -> Option<(C, Vec<N>)>
+where
+    N: Clone + Eq + Hash,
+    C: Copy + Ord + Default + Add<Output = C>,
+    F: FnMut(&N) -> I,
+    G: FnMut(&N) -> bool,
+    I: IntoIterator<Item = (N, C)>,
+{
+    astar(start, is_goal, neighbours, |_| C::default())
+}
+
+pub fn topological_sort<N, F, I>(nodes: &[N], mut neighbours: F) -> Result<Vec<N>, N>
+where
+    N: Clone + Eq + Hash,
+    F: FnMut(&N) -> I,
+    I: IntoIterator<Item = N>,
+{
+    let mut all: Vec<N> = vec![];
+    let mut edges: HashMap<N, Vec<N>> = HashMap::new();
+    let mut in_degree: HashMap<N, usize> = HashMap::new();
+    let mut pending: Vec<N> = nodes.iter().rev().cloned().collect();
+    while let Some(node) = pending.pop() {
+        if edges.contains_key(&node) {
+            continue;
+        }
+        in_degree.entry(node.clone()).or_insert(0);
+        let next: Vec<N> = neighbours(&node).into_iter().collect();
+        for n in next.iter() {
+            *in_degree.entry(n.clone()).or_insert(0) += 1;
+            pending.push(n.clone());
+        }
+        edges.insert(node.clone(), next);
+        all.push(node);
+    }
+
+    let mut queue: VecDeque<N> = all.iter().filter(|n| in_degree[*n] == 0).cloned().collect();
+    let mut order = Vec::with_capacity(all.len());
+    while let Some(node) = queue.pop_front() {
+        for next in edges[&node].iter() {
+            let d = in_degree.get_mut(next).unwrap();
+            *d -= 1;
+            if *d == 0 {
+                queue.push_back(next.clone());
+            }
+        }
+        order.push(node);
+    }
+    match all.into_iter().find(|n| in_degree[n] > 0) {
+        Some(cyclic) => Err(cyclic),
+        None => Ok(order),
+    }
+}
+
+pub fn count_paths<N, F, G, I>(start: N, mut is_goal: G, mut neighbours: F) -> usize
+where
+    N: Clone + Eq + Hash,
+    F: FnMut(&N) -> I,
+    G: FnMut(&N) -> bool,
+    I: IntoIterator<Item = N>,
+{
+    fn visit<N, F, G, I>(
+        node: &N,
+        is_goal: &mut G,
+        neighbours: &mut F,
+        memo: &mut HashMap<N, usize>,
+    ) -> usize
+    where
+        N: Clone + Eq + Hash,
+        F: FnMut(&N) -> I,
+        G: FnMut(&N) -> bool,
+        I: IntoIterator<Item = N>,
+    {
+        if let Some(&count) = memo.get(node) {
+            return count;
+        }
+        let count = if is_goal(node) {
+            1
+        } else {
+            let next: Vec<N> = neighbours(node).into_iter().collect();
+            next.iter()
+                .map(|n| visit(n, is_goal, neighbours, memo))
+                .sum()
+        };
+        memo.insert(node.clone(), count);
+        count
+    }
+    visit(&start, &mut is_goal, &mut neighbours, &mut HashMap::new())
+}
+
+pub fn connected_components<N, F, I>(nodes: &[N], mut neighbours: F) -> Vec<Vec<N>>
+where
+    N: Clone + Eq + Hash,
+    F: FnMut(&N) -> I,
+    I: IntoIterator<Item = N>,
+{
+    let mut seen: HashSet<N> = HashSet::new();
+    let mut components = vec![];
+    for node in nodes.iter() {
+        if seen.contains(node) {
+            continue;
+        }
+        let component = dfs(node.clone(), &mut neighbours);
+        seen.extend(component.iter().cloned());
+        components.push(component);
+    }
+    components
+}
+
+#[derive(Debug)]
+pub struct UnionFind {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+    sets: usize,
+}
+
+impl UnionFind {
+    pub fn new(n: usize) -> Self {
+        UnionFind {
+            parent: (0..n).collect(),
+            size: vec![1; n],
+            sets: n,
+        }
+    }
+
+    pub fn find(&mut self, x: usize) -> usize {
+        let mut root = x;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut x = x;
+        while self.parent[x] != root {
+            (x, self.parent[x]) = (self.parent[x], root);
+        }
+        root
+    }
+
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        let (big, small) = if self.size[a] >= self.size[b] {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        self.parent[small] = big;
+        self.size[big] += self.size[small];
+        self.sets -= 1;
+        true
+    }
+
+    pub fn connected(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    pub fn set_size(&mut self, x: usize) -> usize {
+        let root = self.find(x);
+        self.size[root]
+    }
+
+    pub fn sets(&self) -> usize {
+        self.sets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diamond(n: &char) -> Vec<char> {
+        match n {
+            'a' => vec!['b', 'c'],
+            'b' | 'c' => vec!['d'],
+            'd' => vec!['e'],
+            _ => vec![],
+        }
+    }
+
+    fn weighted(n: &u8) -> Vec<(u8, u32)> {
+        match n {
+            0 => vec![(1, 4), (2, 1)],
+            1 => vec![(3, 1)],
+            2 => vec![(1, 2), (3, 5)],
+            _ => vec![],
+        }
+    }
+
+    #[test]
+    fn unweighted_search() {
+        let dist = bfs('a', diamond);
+        assert_eq!(dist[&'d'], 2);
+        assert_eq!(dist[&'e'], 3);
+        assert_eq!(bfs_path('a', |&n| n == 'e', diamond).unwrap().len(), 4);
+        assert_eq!(bfs_path('d', |&n| n == 'a', diamond), None);
+        assert_eq!(dfs('a', diamond), vec!['a', 'b', 'd', 'e', 'c']);
+
+        let mut adj: AdjList<u8> = HashMap::new();
+        adj.entry(1).or_default().insert(2);
+        adj.entry(2).or_default().insert(1);
+        adj.entry(3).or_default();
+        let components = connected_components(&[1, 2, 3], adjacent(&adj));
+        assert_eq!(components.len(), 2);
+    }
+
+    #[test]
+    fn weighted_search() {
+        let dist = dijkstra(0u8, weighted);
+        assert_eq!(dist[&3], 4);
+        assert_eq!(
+            dijkstra_path(0u8, |&n| n == 3, weighted),
+            Some((4, vec![0, 2, 1, 3]))
+        );
+        let grid = |&(i, j): &(i32, i32)| {
+            [(0, 1), (1, 0), (0, -1), (-1, 0)]
+                .into_iter()
+                .map(move |(di, dj)| ((i + di, j + dj), 1))
+                .filter(|((i, j), _)| {
+                    (0..5).contains(i) && (0..5).contains(j) && (*j != 2 || *i == 4)
+                })
+        };
+        let (cost, path) = astar(
+            (0, 0),
+            |&n| n == (0, 4),
+            grid,
+            |&(i, j)| i.abs_diff(0) + j.abs_diff(4),
+        )
+        .unwrap();
+        assert_eq!(cost, 12);
+        assert_eq!(path.len(), 13);
+    }
+
+    #[test]
+    fn dag_helpers() {
+        let order = topological_sort(&['a'], diamond).unwrap();
+        assert_eq!(order.first(), Some(&'a'));
+        assert_eq!(order.last(), Some(&'e'));
+        assert_eq!(order.len(), 5);
+        assert!(topological_sort(&[0u8], |&n| vec![(n + 1) % 3]).is_err());
+        assert_eq!(count_paths('a', |&n| n == 'e', diamond), 2);
+        assert_eq!(count_paths('a', |&n| n == 'z', diamond), 0);
+    }
+
+    #[test]
+    fn union_find() {
+        let mut uf = UnionFind::new(5);
+        assert!(uf.union(0, 1));
+        assert!(uf.union(3, 4));
+        assert!(!uf.union(1, 0));
+        assert!(uf.connected(0, 1));
+        assert!(!uf.connected(1, 3));
+        assert_eq!(uf.set_size(4), 2);
+        assert_eq!(uf.sets(), 3);
+    }
+}
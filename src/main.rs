@@ -1,8 +1,15 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use std::io::BufReader;
 use std::time::Instant;
 use std::{fs, path::PathBuf};
 
+mod bigint;
 mod days;
+#[allow(dead_code)]
+mod graph;
+mod math;
+mod trace;
+mod visual;
 
 fn validate_day(s: &str) -> Result<u8, String> {
     let day: u8 = s
@@ -26,20 +33,63 @@ fn validate_part(s: &str) -> Result<u8, String> {
     }
 }
 
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run diagnostic queries against the Day05 range database
+    Day05 {
+        #[command(subcommand)]
+        query: days::Day05Query,
+    },
+}
+
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
+    /// Day to solve (implied by the day05 subcommand)
     #[arg(short, long, value_parser = validate_day)]
-    day: u8,
+    day: Option<u8>,
     #[arg(short, long, default_value_t = 1, value_parser = validate_part)]
     part: u8,
     #[arg(short, long)]
     input: PathBuf,
+    #[arg(short, long = "opt", value_name = "KEY=VALUE")]
+    options: Vec<String>,
+    /// Print step-by-step solver events to stderr
+    #[arg(long)]
+    trace: bool,
+    /// Stop tracing after N events (implies --trace)
+    #[arg(long, value_name = "N")]
+    trace_limit: Option<usize>,
+    /// Render solver frames to the terminal or to files
+    #[arg(long, value_name = "MODE")]
+    visualize: Option<visual::Mode>,
+    /// Directory for text and image frames
+    #[arg(long, value_name = "DIR", default_value = "frames")]
+    frames_dir: PathBuf,
+    /// Pixels per cell in image frames
+    #[arg(long, default_value_t = 8)]
+    scale: usize,
+    /// Milliseconds between terminal frames
+    #[arg(long, value_name = "MS", default_value_t = 250)]
+    delay: u64,
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
 fn main() {
     let args = Args::parse();
-    let day = args.day;
+    let day = match (&args.command, args.day) {
+        (Some(Command::Day05 { .. }), None | Some(5)) => 5,
+        (Some(Command::Day05 { .. }), Some(day)) => {
+            eprintln!("Error: the day05 subcommand always runs day 5, but got --day {day}!");
+            return;
+        }
+        (None, Some(day)) => day,
+        (None, None) => {
+            eprintln!("Error: --day is required unless a subcommand is given!");
+            return;
+        }
+    };
     let part = {
         if args.part == 1 {
             days::Part::One
@@ -48,13 +98,67 @@ fn main() {
         }
     };
     let path = args.input;
+    // `-o trace` is Day01's older spelling of --trace.
+    let options = match days::Options::parse(&args.options) {
+        Ok(options) if args.trace || args.trace_limit.is_some() || options.flag("trace") => {
+            options.with_tracer(trace::Tracer::new(args.trace_limit))
+        }
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("Error: {e}!");
+            return;
+        }
+    };
 
-    if let Some(solver) = days::get_day(day) {
+    let options = match args.visualize {
+        Some(mode) => options.with_visualizer(visual::Visualizer::new(
+            mode,
+            args.frames_dir,
+            args.scale,
+            args.delay,
+        )),
+        None => options,
+    };
+
+    if let Some(Command::Day05 { query }) = &args.command {
+        if let Err(e) = options.check(&[]) {
+            eprintln!("Error: {e}!");
+            return;
+        }
         match fs::read_to_string(&path) {
-            Ok(input) => {
+            Ok(input) => match days::run_day05_query(&input, query) {
+                Ok(out) => println!("{out}"),
+                Err(e) => eprintln!("Error: {e}!"),
+            },
+            Err(e) => eprintln!("Error: {e}!"),
+        }
+        return;
+    }
+
+    if let Some(solver) = days::get_day(day) {
+        if let Err(e) = options
+            .check_retired(solver.retired_options())
+            .and_then(|_| options.check(solver.options()))
+        {
+            eprintln!("Error: {e}!");
+            return;
+        }
+        let solved = if solver.streams() {
+            fs::File::open(&path).map(|file| {
+                let mut reader = BufReader::new(file);
                 let start = Instant::now();
-                let solution = solver.solve(part, &input);
-                let duration = start.elapsed();
+                let solution = solver.solve_stream(part, &mut reader, &options);
+                (solution, start.elapsed())
+            })
+        } else {
+            fs::read_to_string(&path).map(|input| {
+                let start = Instant::now();
+                let solution = solver.solve_with(part, &input, &options);
+                (solution, start.elapsed())
+            })
+        };
+        match solved {
+            Ok((Ok(solution), duration)) => {
                 println!("========================================");
                 println!("[ Day {day:02} | Part {part:?} ]");
                 println!(" Solution: {solution}");
@@ -62,9 +166,8 @@ fn main() {
                 println!(" μs:       {} µs", duration.as_micros());
                 println!("========================================");
             }
-            Err(e) => {
-                eprintln!("Error: {e}!")
-            }
+            Ok((Err(e), _)) => eprintln!("Error: {e}!"),
+            Err(e) => eprintln!("Error: {e}!"),
         }
     } else {
         eprintln!("Error: Day{day:02} not implemented yet!")
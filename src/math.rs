@@ -0,0 +1,326 @@
+use std::fmt::Debug;
+use std::ops::{Add, Div, Mul, Rem, Sub};
+
+pub trait Integer:
+    Copy
+    + Ord
+    + Debug
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Rem<Output = Self>
+{
+    const ZERO: Self;
+    const ONE: Self;
+    const TEN: Self;
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+    fn rem_euclid(self, rhs: Self) -> Self;
+}
+
+macro_rules! impl_integer {
+    ($($t:ty),*) => {
+        $(
+            impl Integer for $t {
+                const ZERO: Self = 0;
+                const ONE: Self = 1;
+                const TEN: Self = 10;
+                fn checked_add(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_add(self, rhs)
+                }
+                fn checked_mul(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_mul(self, rhs)
+                }
+                fn rem_euclid(self, rhs: Self) -> Self {
+                    <$t>::rem_euclid(self, rhs)
+                }
+            }
+        )*
+    };
+}
+
+impl_integer!(
+    u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize
+);
+
+pub fn digit_count_base<T: Integer>(mut n: T, base: T) -> u32 {
+    let mut count = 1;
+    while n / base != T::ZERO {
+        n = n / base;
+        count += 1;
+    }
+    count
+}
+
+pub fn digit_count<T: Integer>(n: T) -> u32 {
+    digit_count_base(n, T::TEN)
+}
+
+pub fn pow<T: Integer>(base: T, exp: u32) -> T {
+    checked_pow(base, exp).expect("integer power overflowed")
+}
+
+pub fn checked_pow<T: Integer>(base: T, exp: u32) -> Option<T> {
+    let mut out = T::ONE;
+    for _ in 0..exp {
+        out = out.checked_mul(base)?;
+    }
+    Some(out)
+}
+
+#[allow(dead_code)]
+pub fn pow_table<T: Integer>(base: T) -> Vec<T> {
+    let mut table = vec![T::ONE];
+    while let Some(next) = table.last().and_then(|&p| p.checked_mul(base)) {
+        table.push(next);
+    }
+    table
+}
+
+#[allow(dead_code)]
+pub struct Digits<T> {
+    n: T,
+    base: T,
+    high: T,
+    len: u32,
+}
+
+impl<T: Integer> Iterator for Digits<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        let digit = self.n / self.high % self.base;
+        self.high = self.high / self.base;
+        self.len -= 1;
+        Some(digit)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len as usize, Some(self.len as usize))
+    }
+}
+
+impl<T: Integer> DoubleEndedIterator for Digits<T> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        let digit = self.n % self.base;
+        self.n = self.n / self.base;
+        self.high = self.high / self.base;
+        self.len -= 1;
+        Some(digit)
+    }
+}
+
+impl<T: Integer> ExactSizeIterator for Digits<T> {}
+
+#[allow(dead_code)]
+pub fn digits_base<T: Integer>(n: T, base: T) -> Digits<T> {
+    let len = digit_count_base(n, base);
+    Digits {
+        n,
+        base,
+        high: pow(base, len - 1),
+        len,
+    }
+}
+
+#[allow(dead_code)]
+pub fn digits<T: Integer>(n: T) -> Digits<T> {
+    digits_base(n, T::TEN)
+}
+
+#[allow(dead_code)]
+pub fn concat<T: Integer>(a: T, b: T) -> Option<T> {
+    checked_pow(T::TEN, digit_count(b))?
+        .checked_mul(a)?
+        .checked_add(b)
+}
+
+#[allow(dead_code)]
+pub fn repunit_base<T: Integer>(len: u32, base: T) -> Option<T> {
+    let mut out = T::ZERO;
+    for _ in 0..len {
+        out = out.checked_mul(base)?.checked_add(T::ONE)?;
+    }
+    Some(out)
+}
+
+#[allow(dead_code)]
+pub fn repunit<T: Integer>(len: u32) -> Option<T> {
+    repunit_base(len, T::TEN)
+}
+
+pub fn repeat_pattern_base<T: Integer>(block: T, block_len: u32, times: u32, base: T) -> Option<T> {
+    let shift = checked_pow(base, block_len)?;
+    let mut multiplier = T::ZERO;
+    for _ in 0..times {
+        multiplier = multiplier.checked_mul(shift)?.checked_add(T::ONE)?;
+    }
+    block.checked_mul(multiplier)
+}
+
+#[allow(dead_code)]
+pub fn repeat_pattern<T: Integer>(block: T, block_len: u32, times: u32) -> Option<T> {
+    repeat_pattern_base(block, block_len, times, T::TEN)
+}
+
+#[allow(dead_code)]
+pub fn gcd<T: Integer>(mut a: T, mut b: T) -> T {
+    while b != T::ZERO {
+        (a, b) = (b, a % b);
+    }
+    if a < T::ZERO { T::ZERO - a } else { a }
+}
+
+#[allow(dead_code)]
+pub fn lcm<T: Integer>(a: T, b: T) -> Option<T> {
+    if a == T::ZERO || b == T::ZERO {
+        return Some(T::ZERO);
+    }
+    let l = (a / gcd(a, b)).checked_mul(b)?;
+    Some(if l < T::ZERO { T::ZERO - l } else { l })
+}
+
+pub fn add_mod<T: Integer>(a: T, b: T, m: T) -> T {
+    let (a, b) = (a.rem_euclid(m), b.rem_euclid(m));
+    if a >= m - b { a - (m - b) } else { a + b }
+}
+
+pub fn sub_mod<T: Integer>(a: T, b: T, m: T) -> T {
+    let (a, b) = (a.rem_euclid(m), b.rem_euclid(m));
+    if a >= b { a - b } else { m - (b - a) }
+}
+
+#[allow(dead_code)]
+pub fn mul_mod<T: Integer>(a: T, b: T, m: T) -> T {
+    let (mut a, mut b) = (a.rem_euclid(m), b.rem_euclid(m));
+    if let Some(p) = a.checked_mul(b) {
+        return p % m;
+    }
+    let two = T::ONE + T::ONE;
+    let mut out = T::ZERO;
+    while b > T::ZERO {
+        if b % two == T::ONE {
+            out = add_mod(out, a, m);
+        }
+        a = add_mod(a, a, m);
+        b = b / two;
+    }
+    out
+}
+
+#[allow(dead_code)]
+pub fn pow_mod<T: Integer>(base: T, mut exp: u64, m: T) -> T {
+    let mut base = base.rem_euclid(m);
+    let mut out = T::ONE % m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            out = mul_mod(out, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+    out
+}
+
+#[allow(dead_code)]
+pub fn mod_inverse<T: Integer>(a: T, m: T) -> Option<T> {
+    let (mut old_r, mut r) = (a.rem_euclid(m), m);
+    let (mut old_s, mut s) = (T::ONE % m, T::ZERO);
+    while r != T::ZERO {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_s, s) = (s, sub_mod(old_s, mul_mod(q, s, m), m));
+    }
+    (old_r == T::ONE).then_some(old_s)
+}
+
+#[allow(dead_code)]
+pub fn crt<T: Integer>(congruences: &[(T, T)]) -> Option<(T, T)> {
+    let mut acc = (T::ZERO, T::ONE);
+    for &(r, m) in congruences {
+        let (a1, m1) = acc;
+        let g = gcd(m1, m);
+        let d = sub_mod(r, a1, m);
+        if d % g != T::ZERO {
+            return None;
+        }
+        let m2 = m / g;
+        let t = mul_mod(d / g, mod_inverse(m1 / g, m2)?, m2);
+        let modulus = m1.checked_mul(m2)?;
+        acc = (a1.checked_add(m1.checked_mul(t)?)? % modulus, modulus);
+    }
+    Some(acc)
+}
+
+pub struct XorShift(u64);
+
+impl XorShift {
+    pub fn new(seed: u64) -> Self {
+        XorShift(seed.max(1))
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digit_helpers() {
+        assert_eq!(digit_count(0u32), 1);
+        assert_eq!(digit_count(9u8), 1);
+        assert_eq!(digit_count(1188511885u64), 10);
+        assert_eq!(digit_count(-120i32), 3);
+        assert_eq!(digit_count(u128::MAX), 39);
+        assert_eq!(digit_count_base(255u8, 2), 8);
+        assert_eq!(digits(9051usize).collect::<Vec<_>>(), vec![9, 0, 5, 1]);
+        assert_eq!(
+            digits(9051usize).rev().collect::<Vec<_>>(),
+            vec![1, 5, 0, 9]
+        );
+        assert_eq!(digits_base(6u16, 2).collect::<Vec<_>>(), vec![1, 1, 0]);
+        assert_eq!(concat(12u32, 345), Some(12345));
+        assert_eq!(concat(200u8, 1), None);
+    }
+
+    #[test]
+    fn repeat_helpers() {
+        assert_eq!(repunit::<u64>(5), Some(11111));
+        assert_eq!(repunit_base::<u8>(3, 2), Some(0b111));
+        assert_eq!(repeat_pattern(12u64, 2, 3), Some(121212));
+        assert_eq!(repeat_pattern(7u64, 3, 2), Some(7007));
+        assert_eq!(repeat_pattern(99u8, 2, 2), None);
+        assert_eq!(pow_table(10u16), vec![1, 10, 100, 1000, 10000]);
+        assert_eq!(pow(3i64, 4), 81);
+    }
+
+    #[test]
+    fn number_theory() {
+        assert_eq!(gcd(48u32, 18), 6);
+        assert_eq!(gcd(-48i32, 18), 6);
+        assert_eq!(lcm(4u64, 6), Some(12));
+        assert_eq!(add_mod(95usize, 10, 100), 5);
+        assert_eq!(sub_mod(5usize, 10, 100), 95);
+        assert_eq!(sub_mod(-3i32, 1, 7), 3);
+        assert_eq!(mul_mod(u64::MAX - 1, u64::MAX - 2, u64::MAX), 2);
+        assert_eq!(pow_mod(2u32, 10, 1000), 24);
+        assert_eq!(mod_inverse(3u32, 11), Some(4));
+        assert_eq!(mod_inverse(4u32, 8), None);
+        assert_eq!(crt(&[(2u64, 3), (3, 5), (2, 7)]), Some((23, 105)));
+        assert_eq!(crt(&[(1u32, 4), (3, 6)]), Some((9, 12)));
+        assert_eq!(crt(&[(1u32, 4), (2, 6)]), None);
+    }
+}
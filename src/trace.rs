@@ -0,0 +1,85 @@
+use std::cell::Cell;
+use std::fmt::Display;
+
+/// Sink for step-by-step solver events. A default tracer is disabled, and
+/// `trace!` checks that before evaluating any of its fields.
+#[derive(Clone, Debug, Default)]
+pub struct Tracer {
+    enabled: Cell<bool>,
+    limit: Option<usize>,
+    emitted: Cell<usize>,
+}
+
+impl Tracer {
+    pub fn new(limit: Option<usize>) -> Self {
+        Tracer {
+            enabled: Cell::new(limit != Some(0)),
+            limit,
+            emitted: Cell::new(0),
+        }
+    }
+
+    #[inline]
+    pub fn enabled(&self) -> bool {
+        self.enabled.get()
+    }
+
+    #[cfg(test)]
+    fn emitted(&self) -> usize {
+        self.emitted.get()
+    }
+
+    pub fn emit(&self, event: &str, fields: &[(&str, &dyn Display)]) {
+        let mut line = format!("[trace] {event}");
+        for (key, value) in fields.iter() {
+            line.push_str(&format!(" {key}={value}"));
+        }
+        eprintln!("{line}");
+        self.emitted.set(self.emitted.get() + 1);
+        if let Some(limit) = self.limit
+            && self.emitted.get() >= limit
+        {
+            eprintln!("[trace] limit of {limit} events reached");
+            self.enabled.set(false);
+        }
+    }
+}
+
+macro_rules! trace {
+    ($tracer:expr, $event:literal $(, $key:ident = $value:expr)* $(,)?) => {
+        if $tracer.enabled() {
+            $tracer.emit($event, &[$((stringify!($key), &$value as &dyn std::fmt::Display)),*]);
+        }
+    };
+}
+
+pub(crate) use trace;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limit_disables_tracer() {
+        let tracer = Tracer::default();
+        let mut evaluated = 0;
+        trace!(
+            tracer,
+            "skipped",
+            value = {
+                evaluated += 1;
+                evaluated
+            }
+        );
+        assert_eq!((evaluated, tracer.emitted()), (0, 0));
+
+        let tracer = Tracer::new(Some(2));
+        for i in 0..5 {
+            trace!(tracer, "step", i = i, square = i * i);
+        }
+        assert_eq!(tracer.emitted(), 2);
+        assert!(!tracer.enabled());
+        assert!(!Tracer::new(Some(0)).enabled());
+        assert!(Tracer::new(None).enabled());
+    }
+}
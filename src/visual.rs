@@ -0,0 +1,275 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+pub type Rgb = (u8, u8, u8);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Ink {
+    Plain,
+    Dim,
+    Colour(Rgb),
+    Bold(Rgb),
+}
+
+/// One grid pixel: the symbol and ink used on the terminal, and the fill
+/// colour used for image output.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pixel {
+    pub symbol: char,
+    pub ink: Ink,
+    pub fill: Rgb,
+}
+
+impl Pixel {
+    pub fn plain(symbol: char, fill: Rgb) -> Self {
+        Pixel {
+            symbol,
+            ink: Ink::Plain,
+            fill,
+        }
+    }
+
+    pub fn dim(symbol: char, fill: Rgb) -> Self {
+        Pixel {
+            symbol,
+            ink: Ink::Dim,
+            fill,
+        }
+    }
+
+    pub fn coloured(symbol: char, colour: Rgb) -> Self {
+        Pixel {
+            symbol,
+            ink: Ink::Colour(colour),
+            fill: colour,
+        }
+    }
+
+    pub fn bold(symbol: char, colour: Rgb) -> Self {
+        Pixel {
+            symbol,
+            ink: Ink::Bold(colour),
+            fill: colour,
+        }
+    }
+}
+
+/// Red to blue palette, `t` in `0.0..=1.0`.
+pub fn gradient(t: f64) -> Rgb {
+    let t = t.clamp(0.0, 1.0);
+    (
+        (230.0 * (1.0 - t) + 40.0 * t) as u8,
+        (60.0 + 120.0 * (1.0 - (2.0 * t - 1.0).abs())) as u8,
+        (40.0 * (1.0 - t) + 230.0 * t) as u8,
+    )
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    width: usize,
+    height: usize,
+    pixels: Vec<Pixel>,
+}
+
+impl Frame {
+    pub fn from_fn(width: usize, height: usize, mut f: impl FnMut(usize, usize) -> Pixel) -> Self {
+        let pixels = (0..height)
+            .flat_map(|i| (0..width).map(move |j| (i, j)))
+            .map(|(i, j)| f(i, j))
+            .collect();
+        Frame {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    fn rows(&self) -> impl Iterator<Item = &[Pixel]> {
+        self.pixels.chunks(self.width.max(1))
+    }
+
+    pub fn text(&self) -> String {
+        let mut out = String::with_capacity((self.width + 1) * self.height);
+        for row in self.rows() {
+            out.extend(row.iter().map(|c| c.symbol));
+            out.push('\n');
+        }
+        out
+    }
+
+    pub fn ansi(&self) -> String {
+        let mut out = String::new();
+        for row in self.rows() {
+            for pixel in row.iter() {
+                let c = pixel.symbol;
+                match pixel.ink {
+                    Ink::Plain => out.push(c),
+                    Ink::Dim => out.push_str(&format!("\x1b[2m{c}\x1b[0m")),
+                    Ink::Colour((r, g, b)) => {
+                        out.push_str(&format!("\x1b[38;2;{r};{g};{b}m{c}\x1b[0m"))
+                    }
+                    Ink::Bold((r, g, b)) => {
+                        out.push_str(&format!("\x1b[1;38;2;{r};{g};{b}m{c}\x1b[0m"))
+                    }
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Binary PPM (P6) image, or PGM (P5) when `gray`, with each pixel drawn
+    /// as a `scale` by `scale` square.
+    pub fn image(&self, scale: usize, gray: bool) -> Vec<u8> {
+        let scale = scale.max(1);
+        let (w, h) = (self.width * scale, self.height * scale);
+        let header = if gray {
+            format!("P5\n{w} {h}\n255\n")
+        } else {
+            format!("P6\n{w} {h}\n255\n")
+        };
+        let mut out = header.into_bytes();
+        for y in 0..h {
+            for x in 0..w {
+                let (r, g, b) = self.pixels[(y / scale) * self.width + x / scale].fill;
+                if gray {
+                    out.push(((r as u16 * 3 + g as u16 * 6 + b as u16) / 10) as u8);
+                } else {
+                    out.extend([r, g, b]);
+                }
+            }
+        }
+        out
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    Terminal,
+    Text,
+    Flipbook,
+    Ppm,
+    Pgm,
+}
+
+/// Where solvers send frames. A default visualizer is disabled, so solvers
+/// should check `enabled` before building frames.
+#[derive(Clone, Debug, Default)]
+pub struct Visualizer {
+    mode: Option<Mode>,
+    dir: PathBuf,
+    scale: usize,
+    delay: Duration,
+    shown: RefCell<HashMap<String, usize>>,
+}
+
+impl Visualizer {
+    pub fn new(mode: Mode, dir: PathBuf, scale: usize, delay_ms: u64) -> Self {
+        Visualizer {
+            mode: Some(mode),
+            dir,
+            scale,
+            delay: Duration::from_millis(delay_ms),
+            shown: RefCell::default(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.mode.is_some()
+    }
+
+    pub fn show(&self, name: &str, frame: &Frame) -> Result<(), String> {
+        let n = {
+            let mut shown = self.shown.borrow_mut();
+            let count = shown.entry(name.to_string()).or_default();
+            *count += 1;
+            *count - 1
+        };
+        let (contents, extension) = match self.mode {
+            None => return Ok(()),
+            Some(Mode::Terminal) => {
+                print!("\x1b[2J\x1b[H{}", frame.ansi());
+                println!("{name} frame {n}");
+                thread::sleep(self.delay);
+                return Ok(());
+            }
+            Some(Mode::Text) => (frame.text().into_bytes(), "txt"),
+            Some(Mode::Flipbook) => {
+                let path = self.dir.join(format!("{name}.txt"));
+                let mut file = fs::create_dir_all(&self.dir)
+                    .and_then(|_| {
+                        fs::OpenOptions::new()
+                            .create(true)
+                            .write(true)
+                            .append(n > 0)
+                            .truncate(n == 0)
+                            .open(path)
+                    })
+                    .map_err(|e| e.to_string())?;
+                return write!(file, "-- {name} frame {n} --\n{}", frame.text())
+                    .map_err(|e| e.to_string());
+            }
+            Some(Mode::Ppm) => (frame.image(self.scale, false), "ppm"),
+            Some(Mode::Pgm) => (frame.image(self.scale, true), "pgm"),
+        };
+        let io_error = |e: std::io::Error| e.to_string();
+        fs::create_dir_all(&self.dir).map_err(io_error)?;
+        let path = self.dir.join(format!("{name}_{n:04}.{extension}"));
+        fs::write(path, contents).map_err(io_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_renders_text_ansi_and_images() {
+        let frame = Frame::from_fn(3, 2, |i, j| match (i, j) {
+            (0, 0) => Pixel::coloured('a', (255, 0, 0)),
+            (1, 2) => Pixel::dim('b', (0, 0, 0)),
+            _ => Pixel::plain('.', (255, 255, 255)),
+        });
+        assert_eq!(frame.text(), "a..\n..b\n");
+        assert_eq!(
+            frame.ansi(),
+            "\x1b[38;2;255;0;0ma\x1b[0m..\n..\x1b[2mb\x1b[0m\n"
+        );
+        let image = frame.image(2, false);
+        assert!(image.starts_with(b"P6\n6 4\n255\n"));
+        assert_eq!(image.len(), "P6\n6 4\n255\n".len() + 6 * 4 * 3);
+        let image = frame.image(1, true);
+        assert_eq!(
+            &image["P5\n3 2\n255\n".len()..],
+            &[76, 255, 255, 255, 255, 0]
+        );
+        assert_eq!(gradient(0.0), (230, 60, 40));
+        assert_eq!(gradient(1.0), (40, 60, 230));
+        assert_eq!(gradient(2.0), gradient(1.0));
+    }
+
+    #[test]
+    fn flipbook_collects_frames_in_one_file() {
+        let dir = std::env::temp_dir().join(format!("flipbook_{}", std::process::id()));
+        for _ in 0..2 {
+            let visualizer = Visualizer::new(Mode::Flipbook, dir.clone(), 1, 0);
+            for (name, symbol) in [("first", 'a'), ("second", 'b'), ("second", 'c')] {
+                let frame = Frame::from_fn(2, 1, |_, _| Pixel::plain(symbol, (0, 0, 0)));
+                visualizer.show(name, &frame).unwrap();
+            }
+        }
+        let first = fs::read_to_string(dir.join("first.txt")).unwrap();
+        let second = fs::read_to_string(dir.join("second.txt")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(first, "-- first frame 0 --\naa\n");
+        assert_eq!(
+            second,
+            "-- second frame 0 --\nbb\n-- second frame 1 --\ncc\n"
+        );
+    }
+}